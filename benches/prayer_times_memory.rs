@@ -0,0 +1,119 @@
+//! Peak heap usage of a maximum-range (750 day) prayer times response, loaded
+//! and built in one go versus read in pages and streamed through
+//! `WaktuSolatEncoder`, as the route does for long ranges.
+//!
+//! Run with `cargo bench --bench prayer_times_memory`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use simplesolat_api::{
+    models::prayer_times::SelectPrayerTime,
+    routes::prayer_times::{Adjustments, WaktuSolat, WaktuSolatEncoder, WaktuSolatResponse},
+};
+
+const DAYS: i64 = 751;
+const CHUNK_ROWS: usize = 64;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// Wraps the system allocator and tracks the high-water mark of live bytes.
+struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let now = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+/// Stands in for `select_prayer_times_page`: up to `limit` rows from day
+/// `first` on.
+fn page(first: i64, limit: usize) -> Vec<SelectPrayerTime> {
+    let start = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+    let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+    (first..DAYS.min(first + limit as i64)).map(move |i| SelectPrayerTime {
+        id: i,
+        zone_code: "SGR01".to_string(),
+        date: start + chrono::Duration::days(i),
+        imsak: time(5, 55),
+        fajr: time(6, 5),
+        syuruk: time(7, 12),
+        dhuhr: time(13, 20),
+        asr: time(16, 22),
+        maghrib: time(19, 23),
+        isha: time(20, 33),
+        created_at: NaiveDateTime::default(),
+        updated_at: NaiveDateTime::default(),
+    })
+    .collect()
+}
+
+/// Runs `f` and returns (bytes produced, peak heap growth, elapsed ms).
+fn measure(f: impl FnOnce() -> usize) -> (usize, usize, f64) {
+    let baseline = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    let started = Instant::now();
+    let bytes = f();
+    let elapsed = started.elapsed().as_secs_f64() * 1000.0;
+    (bytes, PEAK.load(Ordering::Relaxed) - baseline, elapsed)
+}
+
+fn buffered() -> usize {
+    let tz = chrono_tz::Asia::Kuala_Lumpur;
+    let adjust = Adjustments::default();
+    let pts = page(0, DAYS as usize);
+    let response = WaktuSolatResponse {
+        data: pts.iter().map(|pt| WaktuSolat::from_prayer_time(pt, tz, &adjust)).collect(),
+        meta: None,
+    };
+    serde_json::to_vec(&response).unwrap().len()
+}
+
+fn streamed() -> usize {
+    let mut encoder = WaktuSolatEncoder::new(chrono_tz::Asia::Kuala_Lumpur, Adjustments::default());
+    let (mut first, mut sent) = (0, 0);
+    let mut chunk = loop {
+        let rows = page(first, CHUNK_ROWS);
+        let mut chunk = Vec::new();
+        for pt in &rows {
+            encoder.push(pt, &mut chunk).unwrap();
+        }
+        if rows.len() < CHUNK_ROWS {
+            break chunk;
+        }
+        first += rows.len() as i64;
+        // Handed to the response body and dropped once written out
+        sent += chunk.len();
+    };
+    encoder.finish(&mut chunk).unwrap();
+    sent + chunk.len()
+}
+
+fn main() {
+    println!("prayer times, {} days", DAYS);
+    for (name, run) in [("buffered", buffered as fn() -> usize), ("streamed", streamed)] {
+        let (bytes, peak, ms) = measure(run);
+        println!(
+            "  {:<9} body {:>7} B   peak heap {:>8} B   {:>6.2} ms",
+            name, bytes, peak, ms
+        );
+    }
+}
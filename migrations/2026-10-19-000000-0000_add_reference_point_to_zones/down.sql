@@ -0,0 +1,2 @@
+ALTER TABLE zones DROP COLUMN longitude;
+ALTER TABLE zones DROP COLUMN latitude;
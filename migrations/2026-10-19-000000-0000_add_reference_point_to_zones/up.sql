@@ -0,0 +1,2 @@
+ALTER TABLE zones ADD COLUMN latitude DOUBLE PRECISION;
+ALTER TABLE zones ADD COLUMN longitude DOUBLE PRECISION;
@@ -0,0 +1,4 @@
+ALTER TABLE zones DROP COLUMN max_longitude;
+ALTER TABLE zones DROP COLUMN max_latitude;
+ALTER TABLE zones DROP COLUMN min_longitude;
+ALTER TABLE zones DROP COLUMN min_latitude;
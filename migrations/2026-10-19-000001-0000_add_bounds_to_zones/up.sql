@@ -0,0 +1,4 @@
+ALTER TABLE zones ADD COLUMN min_latitude DOUBLE PRECISION;
+ALTER TABLE zones ADD COLUMN min_longitude DOUBLE PRECISION;
+ALTER TABLE zones ADD COLUMN max_latitude DOUBLE PRECISION;
+ALTER TABLE zones ADD COLUMN max_longitude DOUBLE PRECISION;
@@ -0,0 +1 @@
+ALTER TABLE prayer_times DROP COLUMN updated_at;
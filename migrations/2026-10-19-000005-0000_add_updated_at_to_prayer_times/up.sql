@@ -0,0 +1,2 @@
+ALTER TABLE prayer_times ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP;
+UPDATE prayer_times SET updated_at = created_at;
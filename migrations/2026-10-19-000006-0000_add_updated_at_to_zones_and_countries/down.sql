@@ -0,0 +1,2 @@
+ALTER TABLE countries DROP COLUMN updated_at;
+ALTER TABLE zones DROP COLUMN updated_at;
@@ -0,0 +1,4 @@
+ALTER TABLE zones ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP;
+UPDATE zones SET updated_at = created_at;
+ALTER TABLE countries ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP;
+UPDATE countries SET updated_at = created_at;
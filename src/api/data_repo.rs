@@ -1,7 +1,9 @@
+use std::collections::HashMap;
+
 use chrono::{NaiveDate, NaiveTime};
 use serde::{self, Deserialize, Deserializer};
 
-const BASE_URL: &str = "https://simplesolat-data.netlify.app";
+use crate::{config::DataRepoConfig, geodesy::Polygon};
 
 /// Deserialize HH:MM or HH:MM:SS time strings.
 fn deserialize_time<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
@@ -65,12 +67,114 @@ pub struct PrayerTimeRecord {
     pub isha: NaiveTime,
 }
 
+/// GeoJSON feature collection referenced by a country's `geojson` field.
+/// Only the parts needed to derive zone geometry are parsed.
+#[derive(Debug, Deserialize)]
+pub struct FeatureCollection {
+    pub features: Vec<Feature>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Feature {
+    #[serde(default)]
+    pub properties: serde_json::Map<String, serde_json::Value>,
+    pub geometry: Option<Geometry>,
+}
+
+/// GeoJSON positions may carry an altitude, so they are read as `Vec<f64>`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum Geometry {
+    Polygon { coordinates: Vec<Vec<Vec<f64>>> },
+    MultiPolygon { coordinates: Vec<Vec<Vec<Vec<f64>>>> },
+    #[serde(other)]
+    Other,
+}
+
+fn to_polygon(rings: &[Vec<Vec<f64>>]) -> Polygon {
+    rings
+        .iter()
+        .map(|ring| {
+            ring.iter()
+                .filter(|p| p.len() >= 2)
+                .map(|p| [p[0], p[1]])
+                .collect()
+        })
+        .collect()
+}
+
+impl Feature {
+    /// Returns the feature's property value as a string, if present.
+    pub fn property(&self, name: &str) -> Option<String> {
+        match self.properties.get(name)? {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Null => None,
+            other => Some(other.to_string()),
+        }
+    }
+
+    /// Returns the feature's polygons; other geometry types yield none.
+    pub fn polygons(&self) -> Vec<Polygon> {
+        match self.geometry {
+            Some(Geometry::Polygon { ref coordinates }) => vec![to_polygon(coordinates)],
+            Some(Geometry::MultiPolygon { ref coordinates }) => {
+                coordinates.iter().map(|p| to_polygon(p)).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Mapping file referenced by a country's `mapping` field: GeoJSON shape
+/// property value (see `shape_property`) to zone code. Accepts either a flat
+/// map or one nested under a `mapping`/`mappings` key.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum MappingConfig {
+    Wrapped {
+        #[serde(alias = "mappings")]
+        mapping: HashMap<String, String>,
+    },
+    Flat(HashMap<String, String>),
+}
+
+/// HTTP client bound to the data repo's base URL.
+#[derive(Clone)]
+pub struct DataRepo {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+impl DataRepo {
+    pub fn new(config: &DataRepoConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout())
+            .build()
+            .expect("failed to build HTTP client");
+        Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Resolves a path from countries.yaml against the data repo, leaving
+    /// absolute URLs untouched.
+    fn resolve_url(&self, path: &str) -> String {
+        if path.starts_with("http://") || path.starts_with("https://") {
+            path.to_string()
+        } else {
+            format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+        }
+    }
+}
+
 /// Fetches countries.yaml from the data repo.
 pub async fn fetch_countries(
-    client: &reqwest::Client,
+    repo: &DataRepo,
 ) -> Result<Vec<Country>, Box<dyn std::error::Error>> {
-    let url = format!("{}/countries.yaml", BASE_URL);
-    let text = client
+    let url = repo.resolve_url("countries.yaml");
+    let text = repo
+        .client
         .get(&url)
         .send()
         .await?
@@ -83,11 +187,11 @@ pub async fn fetch_countries(
 
 /// Fetches zones/{CC}.yaml from the data repo.
 pub async fn fetch_zones(
-    client: &reqwest::Client,
+    repo: &DataRepo,
     country_code: &str,
 ) -> Result<Vec<Zone>, Box<dyn std::error::Error>> {
-    let url = format!("{}/zones/{}.yaml", BASE_URL, country_code);
-    let response = client.get(&url).send().await?;
+    let url = repo.resolve_url(&format!("zones/{}.yaml", country_code));
+    let response = repo.client.get(&url).send().await?;
     if response.status() == reqwest::StatusCode::NOT_FOUND {
         return Ok(Vec::new());
     }
@@ -99,17 +203,17 @@ pub async fn fetch_zones(
 /// Fetches prayer-times/{CC}/{zone}/{year}-{month}.json from the data repo.
 /// Returns empty vec on 404 (data not available yet).
 pub async fn fetch_prayer_times(
-    client: &reqwest::Client,
+    repo: &DataRepo,
     country_code: &str,
     zone_code: &str,
     year: i32,
     month: u32,
 ) -> Result<Vec<PrayerTimeRecord>, Box<dyn std::error::Error + Send + Sync>> {
-    let url = format!(
-        "{}/prayer-times/{}/{}/{}-{:02}.json",
-        BASE_URL, country_code, zone_code, year, month
-    );
-    let response = client.get(&url).send().await?;
+    let url = repo.resolve_url(&format!(
+        "prayer-times/{}/{}/{}-{:02}.json",
+        country_code, zone_code, year, month
+    ));
+    let response = repo.client.get(&url).send().await?;
     if response.status() == reqwest::StatusCode::NOT_FOUND {
         return Ok(Vec::new());
     }
@@ -117,20 +221,56 @@ pub async fn fetch_prayer_times(
     Ok(records)
 }
 
+/// Fetches the GeoJSON boundaries for a country.
+pub async fn fetch_geojson(
+    repo: &DataRepo,
+    path: &str,
+) -> Result<FeatureCollection, Box<dyn std::error::Error>> {
+    let url = repo.resolve_url(path);
+    let collection = repo
+        .client
+        .get(&url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(collection)
+}
+
+/// Fetches the shape-to-zone mapping for a country (JSON or YAML).
+pub async fn fetch_mapping(
+    repo: &DataRepo,
+    path: &str,
+) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let url = repo.resolve_url(path);
+    let text = repo
+        .client
+        .get(&url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    // YAML is a superset of JSON, so one parser covers both formats
+    let mapping = match serde_yaml::from_str(&text)? {
+        MappingConfig::Wrapped { mapping } => mapping,
+        MappingConfig::Flat(mapping) => mapping,
+    };
+    Ok(mapping)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn client() -> reqwest::Client {
-        reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
-            .unwrap()
+    fn repo() -> DataRepo {
+        DataRepo::new(&DataRepoConfig::default())
     }
 
     #[tokio::test]
     async fn test_fetch_countries() {
-        let countries = fetch_countries(&client()).await.unwrap();
+        let countries = fetch_countries(&repo()).await.unwrap();
         assert!(countries.len() >= 5);
         let my = countries.iter().find(|c| c.code == "MY").unwrap();
         assert_eq!(my.name, "Malaysia");
@@ -139,7 +279,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_fetch_zones() {
-        let zones = fetch_zones(&client(), "MY").await.unwrap();
+        let zones = fetch_zones(&repo(), "MY").await.unwrap();
         assert!(zones.len() >= 59);
         let sgr01 = zones.iter().find(|z| z.code == "SGR01").unwrap();
         assert_eq!(sgr01.timezone, "Asia/Kuala_Lumpur");
@@ -147,7 +287,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_fetch_prayer_times() {
-        let records = fetch_prayer_times(&client(), "MY", "SGR01", 2026, 4).await.unwrap();
+        let records = fetch_prayer_times(&repo(), "MY", "SGR01", 2026, 4).await.unwrap();
         assert_eq!(records.len(), 30); // April has 30 days
         assert_eq!(records[0].date, NaiveDate::from_ymd_opt(2026, 4, 1).unwrap());
         assert!(records[0].fajr < records[0].syuruk);
@@ -156,13 +296,49 @@ mod tests {
 
     #[tokio::test]
     async fn test_fetch_prayer_times_404() {
-        let records = fetch_prayer_times(&client(), "MY", "SGR01", 2099, 1).await.unwrap();
+        let records = fetch_prayer_times(&repo(), "MY", "SGR01", 2099, 1).await.unwrap();
         assert!(records.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_fetch_geojson_and_mapping() {
+        let countries = fetch_countries(&repo()).await.unwrap();
+        let sg = countries.iter().find(|c| c.code == "SG").unwrap();
+        let geojson = fetch_geojson(&repo(), &sg.geojson).await.unwrap();
+        assert!(!geojson.features.is_empty());
+        assert!(geojson.features.iter().any(|f| !f.polygons().is_empty()));
+        let mapping = fetch_mapping(&repo(), &sg.mapping).await.unwrap();
+        assert!(mapping.values().any(|zone| zone == "SGP01"));
+    }
+
+    #[test]
+    fn test_parse_feature_polygons() {
+        let collection: FeatureCollection = serde_json::from_str(
+            r#"{"type":"FeatureCollection","features":[
+                {"type":"Feature","properties":{"shapeName":"Gombak"},
+                 "geometry":{"type":"Polygon","coordinates":[[[101,3,0],[102,3,0],[102,4,0],[101,3,0]]]}},
+                {"type":"Feature","properties":{"shapeName":"Point"},
+                 "geometry":{"type":"Point","coordinates":[101,3]}}
+            ]}"#,
+        )
+        .unwrap();
+        let gombak = &collection.features[0];
+        assert_eq!(gombak.property("shapeName").as_deref(), Some("Gombak"));
+        assert_eq!(gombak.polygons()[0][0][1], [102.0, 3.0]);
+        assert!(collection.features[1].polygons().is_empty());
+    }
+
+    #[test]
+    fn test_parse_mapping_config() {
+        let flat: MappingConfig = serde_yaml::from_str(r#"{"Gombak": "SGR01"}"#).unwrap();
+        assert!(matches!(flat, MappingConfig::Flat(m) if m["Gombak"] == "SGR01"));
+        let wrapped: MappingConfig = serde_yaml::from_str("mapping:\n  Gombak: SGR01\n").unwrap();
+        assert!(matches!(wrapped, MappingConfig::Wrapped { mapping } if mapping["Gombak"] == "SGR01"));
+    }
+
     #[tokio::test]
     async fn test_fetch_zones_unknown_country() {
-        let zones = fetch_zones(&client(), "XX").await.unwrap();
+        let zones = fetch_zones(&repo(), "XX").await.unwrap();
         assert!(zones.is_empty());
     }
 }
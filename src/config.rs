@@ -0,0 +1,470 @@
+//! Typed configuration, layered from lowest to highest precedence: built-in
+//! defaults, a TOML file, environment variables and command line flags.
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    routes::cors::{CorsConfig, CorsOverrides, CorsPolicy},
+    service::{
+        rate_limit::{RateLimitPolicy, parse_trusted_proxies},
+        schedule::SyncSchedules,
+    },
+};
+
+/// Read when no file is named with `--config` or `SIMPLESOLAT_CONFIG`, if it
+/// exists in the working directory.
+pub const DEFAULT_CONFIG_FILE: &str = "simplesolat.toml";
+
+const REDACTED: &str = "****";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("cannot read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid config file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: Box<toml::de::Error>,
+    },
+    #[error("invalid {name}: {message}")]
+    Env { name: String, message: String },
+    #[error("invalid configuration: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub data_repo: DataRepoConfig,
+    pub rate_limit: RateLimitPolicy,
+    pub cors: CorsConfig,
+    pub sync: SyncConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncConfig {
+    /// Cron expressions (UTC) by country code, `*` for every other country.
+    pub schedules: SyncSchedules,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub port: u16,
+    /// How long in-flight requests may run after a shutdown signal.
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            port: 3000,
+            shutdown_timeout_secs: 30,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// PostgreSQL connection string; required.
+    pub url: String,
+    pub pool_size: u32,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            pool_size: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DataRepoConfig {
+    pub base_url: String,
+    /// Timeout for each request to the data repo.
+    pub timeout_secs: u64,
+}
+
+impl Default for DataRepoConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://simplesolat-data.netlify.app".to_string(),
+            timeout_secs: 30,
+        }
+    }
+}
+
+impl DataRepoConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+/// Command line flags overriding the file and environment.
+#[derive(Debug, Default, clap::Args)]
+pub struct ConfigArgs {
+    /// Configuration file (TOML) [env: SIMPLESOLAT_CONFIG] [default: ./simplesolat.toml if present]
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// Port the API server listens on [env: PORT]
+    #[arg(long, global = true)]
+    pub port: Option<u16>,
+    /// PostgreSQL connection string [env: DATABASE_URL]
+    #[arg(long, global = true)]
+    pub database_url: Option<String>,
+    /// Maximum database connections [env: DATABASE_POOL_SIZE]
+    #[arg(long, global = true)]
+    pub pool_size: Option<u32>,
+    /// Base URL of the simplesolat-data repo [env: DATA_REPO_URL]
+    #[arg(long, global = true)]
+    pub data_repo_url: Option<String>,
+    /// Timeout for data repo requests, in seconds [env: HTTP_TIMEOUT_SECS]
+    #[arg(long, global = true)]
+    pub http_timeout_secs: Option<u64>,
+}
+
+/// A list given either as an array or as a comma-separated string, so the
+/// same value can be written in TOML or an environment variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringList(pub Vec<String>);
+
+impl StringList {
+    pub fn joined(&self) -> String {
+        self.0.join(",")
+    }
+}
+
+impl From<&str> for StringList {
+    fn from(value: &str) -> Self {
+        Self(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect(),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for StringList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            String(String),
+            List(Vec<String>),
+        }
+
+        Ok(match Raw::deserialize(deserializer)? {
+            Raw::String(value) => Self::from(value.as_str()),
+            Raw::List(items) => Self(items),
+        })
+    }
+}
+
+fn parse_env<T: FromStr>(name: &str, value: &str) -> Result<T, ConfigError> {
+    value.trim().parse().map_err(|_| ConfigError::Env {
+        name: name.to_string(),
+        message: format!("'{}' is not a valid value", value),
+    })
+}
+
+impl Config {
+    /// Loads the configuration for this process and validates it.
+    pub fn load(args: &ConfigArgs) -> Result<Self, ConfigError> {
+        let config = Self::resolve(args)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Layers the file, environment and arguments without validating the
+    /// result, so it can be shown even when it is invalid.
+    pub fn resolve(args: &ConfigArgs) -> Result<Self, ConfigError> {
+        let path = args
+            .config
+            .clone()
+            .or_else(|| std::env::var_os("SIMPLESOLAT_CONFIG").map(PathBuf::from));
+        let mut config = match path {
+            Some(path) => Self::from_file(&path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Self::from_file(Path::new(DEFAULT_CONFIG_FILE))?,
+            None => Self::default(),
+        };
+
+        config.apply_env(|name| std::env::var(name).ok())?;
+        config.apply_args(args);
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        toml::from_str(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source: Box::new(source),
+        })
+    }
+
+    /// Applies environment overrides, looked up through `var`.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        if let Some(value) = var("PORT") {
+            self.server.port = parse_env("PORT", &value)?;
+        }
+        if let Some(value) = var("SHUTDOWN_TIMEOUT_SECS") {
+            self.server.shutdown_timeout_secs = parse_env("SHUTDOWN_TIMEOUT_SECS", &value)?;
+        }
+        if let Some(value) = var("DATABASE_URL") {
+            self.database.url = value;
+        }
+        if let Some(value) = var("DATABASE_POOL_SIZE") {
+            self.database.pool_size = parse_env("DATABASE_POOL_SIZE", &value)?;
+        }
+        if let Some(value) = var("DATA_REPO_URL") {
+            self.data_repo.base_url = value;
+        }
+        if let Some(value) = var("HTTP_TIMEOUT_SECS") {
+            self.data_repo.timeout_secs = parse_env("HTTP_TIMEOUT_SECS", &value)?;
+        }
+        if let Some(value) = var("RATE_LIMIT_PER_MINUTE") {
+            self.rate_limit.units_per_minute = parse_env("RATE_LIMIT_PER_MINUTE", &value)?;
+        }
+        if let Some(value) = var("TRUSTED_PROXIES") {
+            self.rate_limit.trusted_proxies = parse_trusted_proxies(&value).map_err(|message| ConfigError::Env {
+                name: "TRUSTED_PROXIES".to_string(),
+                message,
+            })?;
+        }
+        if let Some(value) = var("SYNC_SCHEDULES") {
+            self.sync.schedules = SyncSchedules::parse(&value).map_err(|message| ConfigError::Env {
+                name: "SYNC_SCHEDULES".to_string(),
+                message,
+            })?;
+        }
+
+        for (prefix, policy) in [("CORS", &mut self.cors.public), ("ADMIN_CORS", &mut self.cors.admin)] {
+            apply_cors_env(prefix, policy, &var)?;
+        }
+        Ok(())
+    }
+
+    pub fn apply_args(&mut self, args: &ConfigArgs) {
+        if let Some(port) = args.port {
+            self.server.port = port;
+        }
+        if let Some(url) = &args.database_url {
+            self.database.url = url.clone();
+        }
+        if let Some(pool_size) = args.pool_size {
+            self.database.pool_size = pool_size;
+        }
+        if let Some(url) = &args.data_repo_url {
+            self.data_repo.base_url = url.clone();
+        }
+        if let Some(secs) = args.http_timeout_secs {
+            self.data_repo.timeout_secs = secs;
+        }
+    }
+
+    /// Checks values that parse but cannot work, reporting all of them.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+
+        if self.database.url.is_empty() {
+            errors.push("database.url is required (set DATABASE_URL)".to_string());
+        } else if !["postgres://", "postgresql://"]
+            .iter()
+            .any(|scheme| self.database.url.starts_with(scheme))
+        {
+            errors.push("database.url must be a postgres:// URL".to_string());
+        }
+        if self.database.pool_size == 0 {
+            errors.push("database.pool_size must be at least 1".to_string());
+        }
+        if reqwest::Url::parse(&self.data_repo.base_url)
+            .map_or(true, |url| !["http", "https"].contains(&url.scheme()))
+        {
+            errors.push(format!("data_repo.base_url is not an http(s) URL: {}", self.data_repo.base_url));
+        }
+        if self.data_repo.timeout_secs == 0 {
+            errors.push("data_repo.timeout_secs must be at least 1".to_string());
+        }
+        if self.rate_limit.units_per_minute < 1 {
+            errors.push("rate_limit.units_per_minute must be at least 1".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+
+    /// Copy safe to print: the database password is masked, whether in the
+    /// userinfo or in a `password` query parameter.
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        config.database.url = match reqwest::Url::parse(&self.database.url) {
+            Ok(mut url) => {
+                if url.password().is_some() {
+                    let _ = url.set_password(Some(REDACTED));
+                }
+                if url.query_pairs().any(|(name, _)| name == "password") {
+                    let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+                    let mut query = url.query_pairs_mut();
+                    query.clear();
+                    for (name, value) in &pairs {
+                        query.append_pair(name, if name == "password" { REDACTED } else { value });
+                    }
+                }
+                url.to_string()
+            }
+            Err(_) if self.database.url.is_empty() => String::new(),
+            // Not a URL (e.g. key=value form); it may hold a password anywhere
+            Err(_) => REDACTED.to_string(),
+        };
+        config
+    }
+}
+
+/// Reads `{prefix}_ALLOWED_ORIGINS`, `{prefix}_ALLOWED_METHODS`,
+/// `{prefix}_ALLOWED_HEADERS` and `{prefix}_MAX_AGE` (seconds).
+fn apply_cors_env(
+    prefix: &str,
+    policy: &mut CorsPolicy,
+    var: &impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    let list = |name: &str| var(&format!("{}_{}", prefix, name)).map(|v| StringList::from(v.as_str()));
+    let max_age = format!("{}_MAX_AGE", prefix);
+    let overrides = CorsOverrides {
+        allowed_origins: list("ALLOWED_ORIGINS"),
+        allowed_methods: list("ALLOWED_METHODS"),
+        allowed_headers: list("ALLOWED_HEADERS"),
+        max_age_secs: var(&max_age).map(|v| parse_env(&max_age, &v)).transpose()?,
+    };
+    policy.apply(overrides).map_err(|message| ConfigError::Env {
+        name: format!("{}_*", prefix),
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use axum::http::Method;
+
+    use super::*;
+    use crate::routes::cors::AllowedOrigins;
+
+    const FILE: &str = r#"
+        [server]
+        port = 8080
+
+        [database]
+        url = "postgres://app:s3cret@db/simplesolat_db"
+
+        [rate_limit]
+        trusted_proxies = ["10.0.0.0/8", "192.168.1.1"]
+
+        [cors.admin]
+        allowed_origins = "https://ops.example"
+
+        [sync.schedules]
+        "*" = "0 3 * * *"
+        sg = "0 4 * * 1"
+    "#;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let mut config: Config = toml::from_str(FILE).unwrap();
+        assert_eq!(config.server.port, 8080);
+        assert_eq!(config.database.pool_size, 10);
+        assert_eq!(config.rate_limit.trusted_proxies.len(), 2);
+        // A partial group keeps that group's defaults
+        assert_eq!(config.cors.admin.allowed_methods, vec![Method::GET, Method::POST]);
+        assert_eq!(config.cors.public.allowed_origins, AllowedOrigins::Any);
+        assert_eq!(config.sync.schedules.for_country("SG").unwrap().as_str(), "0 4 * * 1");
+
+        config
+            .apply_env(env(&[("PORT", "9000"), ("DATABASE_POOL_SIZE", "4"), ("CORS_ALLOWED_ORIGINS", "https://a.example")]))
+            .unwrap();
+        assert_eq!(config.server.port, 9000);
+        assert_eq!(config.database.pool_size, 4);
+        assert_ne!(config.cors.public.allowed_origins, AllowedOrigins::Any);
+
+        config.apply_args(&ConfigArgs {
+            port: Some(9100),
+            ..Default::default()
+        });
+        assert_eq!(config.server.port, 9100);
+        assert_eq!(config.database.pool_size, 4);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_rejects_invalid_values() {
+        assert!(toml::from_str::<Config>("[server]\nprot = 1").is_err());
+        assert!(toml::from_str::<Config>("[cors.public]\nallowed_origins = [\"example.com\"]").is_err());
+        assert!(Config::default().apply_env(env(&[("PORT", "http")])).is_err());
+        assert!(Config::default().apply_env(env(&[("TRUSTED_PROXIES", "10.0.0.0/40")])).is_err());
+        assert!(toml::from_str::<Config>("[sync.schedules]\nMY = \"daily\"").is_err());
+
+        let mut config = Config::default();
+        config.database.pool_size = 0;
+        config.data_repo.base_url = "ftp://example.com".to_string();
+        let Err(ConfigError::Invalid(errors)) = config.validate() else {
+            panic!("config should be invalid");
+        };
+        assert_eq!(errors.len(), 3);
+    }
+
+    #[test]
+    fn test_redacted_masks_password() {
+        let config: Config = toml::from_str(FILE).unwrap();
+        let printed = toml::to_string(&config.redacted()).unwrap();
+        assert!(printed.contains("postgres://app:****@db/simplesolat_db"));
+        assert!(!printed.contains("s3cret"));
+
+        // The printed form loads back to the same settings
+        let reloaded: Config = toml::from_str(&printed).unwrap();
+        assert_eq!(reloaded.rate_limit.trusted_proxies, config.rate_limit.trusted_proxies);
+        assert_eq!(reloaded.cors.admin.allowed_origins, config.cors.admin.allowed_origins);
+
+        // A password given as a query parameter
+        let mut config = Config::default();
+        config.database.url = "postgres://db/simplesolat_db?user=app&password=s3cret&sslmode=require".to_string();
+        assert_eq!(
+            config.redacted().database.url,
+            "postgres://db/simplesolat_db?user=app&password=****&sslmode=require"
+        );
+    }
+}
@@ -0,0 +1,162 @@
+/// Coordinates of the Kaaba in Makkah (degrees).
+pub const KAABA_LATITUDE: f64 = 21.422_487;
+pub const KAABA_LONGITUDE: f64 = 39.826_206;
+
+/// Mean Earth radius (IUGG) in kilometres.
+const EARTH_RADIUS_KM: f64 = 6_371.008_8;
+
+/// Whether the latitude/longitude pair is within valid WGS84 ranges.
+pub fn is_valid_coordinate(lat: f64, lng: f64) -> bool {
+    (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lng)
+}
+
+/// Initial great-circle bearing from one point to another, in degrees
+/// clockwise from true north (0..360).
+pub fn initial_bearing(lat1: f64, lng1: f64, lat2: f64, lng2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let delta_lambda = (lng2 - lng1).to_radians();
+    let y = delta_lambda.sin() * phi2.cos();
+    let x = phi1.cos() * phi2.sin() - phi1.sin() * phi2.cos() * delta_lambda.cos();
+    y.atan2(x).to_degrees().rem_euclid(360.0)
+}
+
+/// Great-circle distance between two points in kilometres (haversine).
+pub fn distance_km(lat1: f64, lng1: f64, lat2: f64, lng2: f64) -> f64 {
+    let (phi1, phi2) = (lat1.to_radians(), lat2.to_radians());
+    let delta_phi = (lat2 - lat1).to_radians();
+    let delta_lambda = (lng2 - lng1).to_radians();
+    let a = (delta_phi / 2.0).sin().powi(2)
+        + phi1.cos() * phi2.cos() * (delta_lambda / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+/// Qibla bearing (degrees from true north) from the given point.
+pub fn qibla_bearing(lat: f64, lng: f64) -> f64 {
+    initial_bearing(lat, lng, KAABA_LATITUDE, KAABA_LONGITUDE)
+}
+
+/// Distance from the given point to the Kaaba in kilometres.
+pub fn qibla_distance_km(lat: f64, lng: f64) -> f64 {
+    distance_km(lat, lng, KAABA_LATITUDE, KAABA_LONGITUDE)
+}
+
+/// A polygon as GeoJSON rings of `[lng, lat]` positions; the first ring is
+/// the exterior and the rest are holes.
+pub type Polygon = Vec<Vec<[f64; 2]>>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub min_lng: f64,
+    pub max_lat: f64,
+    pub max_lng: f64,
+}
+
+/// Smallest latitude/longitude box containing every position of the polygons.
+pub fn bounding_box(polygons: &[Polygon]) -> Option<BoundingBox> {
+    let mut positions = polygons.iter().flatten().flatten();
+    let first = positions.next()?;
+    let init = BoundingBox {
+        min_lat: first[1],
+        min_lng: first[0],
+        max_lat: first[1],
+        max_lng: first[0],
+    };
+    Some(positions.fold(init, |b, p| BoundingBox {
+        min_lat: b.min_lat.min(p[1]),
+        min_lng: b.min_lng.min(p[0]),
+        max_lat: b.max_lat.max(p[1]),
+        max_lng: b.max_lng.max(p[0]),
+    }))
+}
+
+/// Signed area (shoelace) and area-weighted centroid sums of a ring.
+fn ring_moments(ring: &[[f64; 2]]) -> (f64, f64, f64) {
+    let (mut area, mut cx, mut cy) = (0.0, 0.0, 0.0);
+    for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+        let cross = a[0] * b[1] - b[0] * a[1];
+        area += cross;
+        cx += (a[0] + b[0]) * cross;
+        cy += (a[1] + b[1]) * cross;
+    }
+    (area / 2.0, cx / 6.0, cy / 6.0)
+}
+
+/// Area-weighted centroid `(lat, lng)` of the polygons, treating lng/lat as
+/// planar coordinates. Holes are subtracted regardless of ring winding.
+/// Falls back to the bounding box centre for degenerate (zero-area) input.
+pub fn centroid(polygons: &[Polygon]) -> Option<(f64, f64)> {
+    let (mut area, mut cx, mut cy) = (0.0, 0.0, 0.0);
+    for polygon in polygons {
+        for (i, ring) in polygon.iter().enumerate() {
+            let (a, x, y) = ring_moments(ring);
+            // Normalise the winding so exteriors add and holes subtract
+            let sign = if (a >= 0.0) == (i == 0) { 1.0 } else { -1.0 };
+            area += sign * a;
+            cx += sign * x;
+            cy += sign * y;
+        }
+    }
+
+    if area.abs() > f64::EPSILON {
+        return Some((cy / area, cx / area));
+    }
+    bounding_box(polygons).map(|b| ((b.min_lat + b.max_lat) / 2.0, (b.min_lng + b.max_lng) / 2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qibla_from_kuala_lumpur() {
+        let bearing = qibla_bearing(3.139, 101.6869);
+        let distance = qibla_distance_km(3.139, 101.6869);
+        assert!((bearing - 292.5).abs() < 0.5, "bearing was {}", bearing);
+        assert!((distance - 7_000.0).abs() < 100.0, "distance was {}", distance);
+    }
+
+    #[test]
+    fn test_cardinal_bearings() {
+        assert!((initial_bearing(0.0, 0.0, 10.0, 0.0) - 0.0).abs() < 1e-9);
+        assert!((initial_bearing(0.0, 0.0, 0.0, 10.0) - 90.0).abs() < 1e-9);
+        assert!((initial_bearing(0.0, 0.0, -10.0, 0.0) - 180.0).abs() < 1e-9);
+        assert!((initial_bearing(0.0, 0.0, 0.0, -10.0) - 270.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_is_valid_coordinate() {
+        assert!(is_valid_coordinate(3.1, 101.7));
+        assert!(!is_valid_coordinate(91.0, 0.0));
+        assert!(!is_valid_coordinate(0.0, -181.0));
+        assert!(!is_valid_coordinate(f64::NAN, 0.0));
+    }
+
+    fn square(min: f64, max: f64) -> Vec<[f64; 2]> {
+        vec![[min, min], [max, min], [max, max], [min, max], [min, min]]
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let polygons = vec![vec![square(0.0, 2.0)], vec![square(5.0, 6.0)]];
+        let b = bounding_box(&polygons).unwrap();
+        assert_eq!(b, BoundingBox { min_lat: 0.0, min_lng: 0.0, max_lat: 6.0, max_lng: 6.0 });
+        assert!(bounding_box(&[]).is_none());
+    }
+
+    #[test]
+    fn test_centroid_weights_by_area_and_subtracts_holes() {
+        let (lat, lng) = centroid(&[vec![square(0.0, 2.0)]]).unwrap();
+        assert!((lat - 1.0).abs() < 1e-9 && (lng - 1.0).abs() < 1e-9);
+
+        // A large square dominates a small one far away
+        let (lat, _) = centroid(&[vec![square(0.0, 2.0)], vec![square(10.0, 10.5)]]).unwrap();
+        assert!(lat < 2.0);
+
+        // Clockwise exterior with a counter-clockwise hole in the upper-right corner
+        let mut exterior = square(0.0, 4.0);
+        exterior.reverse();
+        let (lat, lng) = centroid(&[vec![exterior, square(2.0, 4.0)]]).unwrap();
+        assert!(lat < 2.0 && lng < 2.0);
+    }
+}
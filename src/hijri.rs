@@ -0,0 +1,99 @@
+use chrono::{Days, NaiveDate};
+
+/// 1 Muharram 1 AH in the proleptic Gregorian calendar (civil epoch).
+fn epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(622, 7, 19).expect("invalid hijri epoch")
+}
+
+/// Day offsets between the tabular calendar and the dates announced by each
+/// country's religious authority (rukyah/hisab). Countries not listed use 0.
+const COUNTRY_ADJUSTMENTS: &[(&str, i64)] = &[
+    ("BN", 1),
+    ("ID", 1),
+    ("LK", 1),
+    ("MY", 1),
+    ("SG", 1),
+];
+
+pub const RAMADAN: u32 = 9;
+
+/// Returns the day offset to apply to tabular dates for a country.
+pub fn country_adjustment(country: &str) -> i64 {
+    COUNTRY_ADJUSTMENTS
+        .iter()
+        .find(|(code, _)| *code == country)
+        .map(|(_, days)| *days)
+        .unwrap_or(0)
+}
+
+/// Converts a date in the tabular (arithmetical) Islamic calendar to Gregorian.
+/// Returns `None` for out-of-range months/days or years before 1 AH.
+pub fn to_gregorian(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    if year < 1 || !(1..=12).contains(&month) || day < 1 || day > month_length(year, month) {
+        return None;
+    }
+    let year = year as u64;
+    let month = month as u64;
+    let days = (day as u64 - 1) + (59 * (month - 1)).div_ceil(2) + (year - 1) * 354 + (3 + 11 * year) / 30;
+    epoch().checked_add_days(Days::new(days))
+}
+
+/// Whether the given Hijri year has 355 days (30-year cycle, Kuwaiti variant).
+pub fn is_leap_year(year: i32) -> bool {
+    (14 + 11 * year).rem_euclid(30) < 11
+}
+
+/// Number of days in a Hijri month: odd months have 30 days, even months 29,
+/// with Dhu al-Hijjah extended to 30 in leap years.
+pub fn month_length(year: i32, month: u32) -> u32 {
+    if month % 2 == 1 || (month == 12 && is_leap_year(year)) {
+        30
+    } else {
+        29
+    }
+}
+
+/// First and last Gregorian day of Ramadan for a Hijri year, shifted by the
+/// country's adjustment.
+pub fn ramadan_range(year: i32, country: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let start = to_gregorian(year, RAMADAN, 1)?;
+    let end = to_gregorian(year, RAMADAN, month_length(year, RAMADAN))?;
+    let shift = |date: NaiveDate| date.checked_add_signed(chrono::Duration::days(country_adjustment(country)));
+    Some((shift(start)?, shift(end)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_to_gregorian() {
+        assert_eq!(to_gregorian(1, 1, 1), Some(date(622, 7, 19)));
+        assert_eq!(to_gregorian(1446, 9, 1), Some(date(2025, 3, 1)));
+        assert_eq!(to_gregorian(1447, 9, 1), Some(date(2026, 2, 18)));
+        assert_eq!(to_gregorian(1447, 13, 1), None);
+        assert_eq!(to_gregorian(1447, 2, 30), None);
+    }
+
+    #[test]
+    fn test_consecutive_months_are_contiguous() {
+        for month in 1..12 {
+            let last = to_gregorian(1447, month, month_length(1447, month)).unwrap();
+            let next = to_gregorian(1447, month + 1, 1).unwrap();
+            assert_eq!(next - last, chrono::Duration::days(1));
+        }
+    }
+
+    #[test]
+    fn test_ramadan_range_with_country_adjustment() {
+        let (start, end) = ramadan_range(1447, "MY").unwrap();
+        assert_eq!(start, date(2026, 2, 19));
+        assert_eq!(end, date(2026, 3, 20));
+        let (start, _) = ramadan_range(1447, "XX").unwrap();
+        assert_eq!(start, date(2026, 2, 18));
+    }
+}
@@ -1,4 +1,7 @@
 pub mod api;
+pub mod config;
+pub mod geodesy;
+pub mod hijri;
 pub mod models;
 pub mod routes;
 pub mod schema;
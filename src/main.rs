@@ -1,10 +1,19 @@
 use std::net::SocketAddr;
 use std::time::Duration;
 
+use chrono::NaiveDate;
 use clap::{Parser, Subcommand};
+use simplesolat_api::api::data_repo::DataRepo;
+use simplesolat_api::config::{Config, ConfigArgs};
 use simplesolat_api::models::db::connect_db;
+use simplesolat_api::models::zones::select_zone_by_code;
 use simplesolat_api::routes::create_app_router;
 use simplesolat_api::service;
+use simplesolat_api::service::dry_run::SyncDiff;
+use simplesolat_api::service::jobs::SyncJobs;
+use simplesolat_api::service::schedule::{SyncSchedules, load_plan};
+use simplesolat_api::service::shutdown::Shutdown;
+use simplesolat_api::service::sync::{SyncOptions, SyncOutcome, SyncProgress};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[derive(Parser)]
@@ -13,20 +22,106 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    #[command(flatten)]
+    config: ConfigArgs,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Start the API server
-    Serve,
+    Serve {
+        /// Also sync all countries in the background at this interval (e.g. 6h).
+        /// Only one replica syncs at a time.
+        #[arg(long, conflicts_with = "sync_scheduled")]
+        sync_every: Option<String>,
+        /// Also sync in the background following the configured schedules
+        /// (sync.schedules). Only one replica syncs at a time.
+        #[arg(long)]
+        sync_scheduled: bool,
+    },
     /// Sync prayer times data from simplesolat-data repo
     Sync {
         /// Country code to sync (e.g. MY, SG, ID, BN, LK). Omit for all.
-        #[arg(long)]
+        #[arg(long, value_parser = parse_code)]
         country: Option<String>,
+        /// Only sync this zone (e.g. SGR01); its country is looked up if not given
+        #[arg(long, value_parser = parse_code)]
+        zone: Option<String>,
+        /// First month to fetch, YYYY-MM (default: each zone's first missing month)
+        #[arg(long, value_parser = parse_month)]
+        from: Option<NaiveDate>,
+        /// Last month to fetch, YYYY-MM (default: December next year)
+        #[arg(long, value_parser = parse_month)]
+        to: Option<NaiveDate>,
+        /// Refetch the months from --from on and overwrite stored days that differ
+        #[arg(long, requires = "from", conflicts_with = "loop")]
+        force: bool,
         /// Run sync in a loop with the given interval (e.g. 6h, 30m, 1d)
         #[arg(long)]
         r#loop: Option<String>,
+        /// When another process is syncing a country, wait for it to finish
+        /// (default: exit with an error once the other countries are done)
+        #[arg(long, conflicts_with = "skip_if_locked")]
+        wait: bool,
+        /// When another process is syncing a country, skip it and succeed
+        #[arg(long)]
+        skip_if_locked: bool,
+        /// Keep running, syncing each country at its configured schedule (sync.schedules)
+        #[arg(long, conflicts_with_all = ["country", "zone", "from", "to", "loop", "dry_run"])]
+        scheduled: bool,
+        /// Print what the sync would change without writing anything
+        #[arg(long, conflicts_with_all = ["loop", "wait", "skip_if_locked"])]
+        dry_run: bool,
+        /// Print the dry run as JSON
+        #[arg(long, requires = "dry_run")]
+        json: bool,
+    },
+    /// Show the sync schedule with last and next run times (UTC)
+    Schedule,
+    /// Manage API keys
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommand,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the effective configuration as TOML, with secrets redacted,
+    /// followed by any validation errors
+    Print,
+}
+
+#[derive(Subcommand)]
+enum KeysCommand {
+    /// Create a key and print it (it is not stored in plain text)
+    Create {
+        /// Who the key is for
+        #[arg(long)]
+        name: String,
+        /// Sustained rate in cost units per minute: one per request, more for
+        /// long prayer time ranges and /coverage. Bursts of up to one
+        /// minute's worth are allowed.
+        #[arg(long, default_value_t = service::api_keys::DEFAULT_REQUESTS_PER_MINUTE)]
+        requests_per_minute: i32,
+        /// Requests allowed per UTC day, each counting once whatever its
+        /// cost. Omit for no quota.
+        #[arg(long)]
+        daily_quota: Option<i64>,
+        /// Also allow the key to use the /admin endpoints
+        #[arg(long)]
+        admin: bool,
+    },
+    /// List all keys
+    List,
+    /// Revoke a key by id
+    Revoke {
+        id: i64,
     },
 }
 
@@ -46,21 +141,193 @@ fn parse_duration(s: &str) -> Result<Duration, String> {
     }
 }
 
-async fn run_sync(country: &Option<String>, conn: &mut diesel::PgConnection) {
+/// First day of a `YYYY-MM` month.
+fn parse_month(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", s.trim()), "%Y-%m-%d")
+        .map_err(|_| format!("invalid month: {}, use YYYY-MM", s))
+}
+
+/// Country or zone code in the stored case, so `sgr01` finds `SGR01`.
+fn parse_code(s: &str) -> Result<String, String> {
+    Ok(s.trim().to_uppercase())
+}
+
+/// Returns the countries skipped because another process was syncing them.
+async fn run_sync(
+    country: &Option<String>,
+    conn: &mut diesel::PgConnection,
+    repo: &DataRepo,
+    options: &SyncOptions,
+    shutdown: &Shutdown,
+) -> Vec<String> {
+    let progress = SyncProgress::default();
     match country {
         Some(code) => {
             tracing::info!("syncing country: {}", code);
-            service::sync::sync_country(conn, code).await;
+            match service::sync::sync_country(conn, repo, code, options, &progress, shutdown).await {
+                SyncOutcome::Ran => Vec::new(),
+                SyncOutcome::Skipped => vec![code.clone()],
+            }
         }
         None => {
             tracing::info!("syncing all countries");
-            service::sync::sync_all(conn).await;
+            service::sync::sync_all(conn, repo, options, &progress, shutdown).await
+        }
+    }
+}
+
+/// The configured schedules, or exit when there are none to follow.
+fn require_schedules(config: &Config) -> SyncSchedules {
+    if config.sync.schedules.is_empty() {
+        tracing::error!("no sync schedules configured; set [sync.schedules] or SYNC_SCHEDULES");
+        std::process::exit(1);
+    }
+    config.sync.schedules.clone()
+}
+
+fn print_schedule(conn: &mut diesel::PgConnection, schedules: &SyncSchedules) {
+    let now = chrono::Utc::now().naive_utc();
+    let plan = load_plan(conn, schedules, now).unwrap_or_else(|e| {
+        tracing::error!("failed to load sync schedule: {}", e);
+        std::process::exit(1);
+    });
+    println!("{:<8} {:<20} {:<17}  next run", "country", "schedule", "last run");
+    for scheduled in plan {
+        println!(
+            "{:<8} {:<20} {:<17}  {}",
+            scheduled.country,
+            scheduled.schedule,
+            scheduled
+                .last_run_at
+                .map_or("never".to_string(), |t| t.format("%Y-%m-%d %H:%M").to_string()),
+            if scheduled.next_run_at <= now {
+                "due".to_string()
+            } else {
+                scheduled.next_run_at.format("%Y-%m-%d %H:%M").to_string()
+            },
+        );
+    }
+}
+
+fn print_diff(diff: &SyncDiff, force: bool) {
+    for country in &diff.countries {
+        if country.is_empty() {
+            println!("{}: no changes", country.country);
+            continue;
+        }
+        println!("{}:", country.country);
+        if !country.new_zones.is_empty() {
+            println!("  new zones: {}", country.new_zones.join(", "));
+        }
+        if !country.removed_zones.is_empty() {
+            println!("  removed zones (kept by sync): {}", country.removed_zones.join(", "));
+        }
+        for change in country.renamed_zones.iter().chain(&country.timezone_changes) {
+            println!(
+                "  {} {}: {:?} -> {:?}",
+                change.zone, change.field, change.stored, change.fetched
+            );
+        }
+        for zone in &country.prayer_times {
+            if !zone.inserted.is_empty() {
+                let ranges: Vec<String> = zone
+                    .inserted
+                    .iter()
+                    .map(|r| if r.from == r.to { r.from.to_string() } else { format!("{} to {}", r.from, r.to) })
+                    .collect();
+                println!("  {} +{} days: {}", zone.zone, zone.inserted_days(), ranges.join(", "));
+            }
+            if !zone.changed.is_empty() {
+                let days: Vec<_> = zone.changed.chunk_by(|a, b| a.date == b.date).collect();
+                println!(
+                    "  {} {} changed days ({}):",
+                    zone.zone,
+                    days.len(),
+                    if force { "overwritten" } else { "kept without --force" }
+                );
+                for day in days {
+                    let times: Vec<String> = day
+                        .iter()
+                        .map(|c| format!("{} {} -> {}", c.prayer, c.stored.format("%H:%M"), c.fetched.format("%H:%M")))
+                        .collect();
+                    println!("    {}: {}", day[0].date, times.join(", "));
+                }
+            }
+        }
+    }
+    if !diff.errors.is_empty() {
+        println!("errors:");
+        for error in &diff.errors {
+            println!("  {}", error);
         }
     }
 }
 
+fn run_keys(command: KeysCommand, conn: &mut diesel::PgConnection) {
+    use simplesolat_api::models::api_keys::{revoke_api_key, select_api_key_usage, select_api_keys};
+
+    let result = match command {
+        KeysCommand::Create {
+            name,
+            requests_per_minute,
+            daily_quota,
+            admin,
+        } => service::api_keys::create_key(conn, &name, requests_per_minute, daily_quota, admin).map(|(key, record)| {
+            println!(
+                "created {}key {} for {}",
+                if record.admin { "admin " } else { "" },
+                record.id,
+                record.name
+            );
+            println!("{}", key);
+            println!("store it now; it cannot be shown again");
+        }),
+        KeysCommand::List => select_api_keys(conn).and_then(|keys| {
+            let today = chrono::Utc::now().date_naive();
+            println!(
+                "{:>4}  {:<12} {:<24} {:>8} {:>10} {:>10}  {:<19}  status",
+                "id", "prefix", "name", "rpm", "quota", "today", "created"
+            );
+            for key in keys {
+                let used = select_api_key_usage(conn, key.id, today)?;
+                println!(
+                    "{:>4}  {:<12} {:<24} {:>8} {:>10} {:>10}  {:<19}  {}",
+                    key.id,
+                    key.key_prefix,
+                    key.name,
+                    key.requests_per_minute,
+                    key.daily_quota.map_or("-".to_string(), |q| q.to_string()),
+                    used,
+                    key.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    match key.revoked_at {
+                        Some(t) => format!("revoked {}", t.format("%Y-%m-%d")),
+                        None if key.admin => "active, admin".to_string(),
+                        None => "active".to_string(),
+                    },
+                );
+            }
+            Ok(())
+        }),
+        KeysCommand::Revoke { id } => revoke_api_key(conn, id).map(|revoked| {
+            if revoked {
+                println!("revoked key {}", id);
+            } else {
+                println!("no active key with id {}", id);
+            }
+        }),
+    };
+
+    if let Err(e) = result {
+        tracing::error!("keys command failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
 #[tokio::main]
 async fn main() {
+    // Values from .env never override variables already set
+    dotenvy::dotenv().ok();
+
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
@@ -70,28 +337,154 @@ async fn main() {
         .init();
 
     let cli = Cli::parse();
+    let config = Config::resolve(&cli.config).unwrap_or_else(|e| {
+        tracing::error!("{}", e);
+        std::process::exit(1);
+    });
+    // `config print` shows an invalid configuration before reporting it
+    if !matches!(cli.command, Some(Commands::Config { .. }))
+        && let Err(e) = config.validate()
+    {
+        tracing::error!("{}", e);
+        std::process::exit(1);
+    }
 
     match cli.command {
-        None | Some(Commands::Serve) => {
-            let router = create_app_router().await;
+        None | Some(Commands::Serve { .. }) => {
+            let sync_every = match cli.command {
+                Some(Commands::Serve { sync_every: Some(ref s), .. }) => Some(parse_duration(s).unwrap_or_else(|e| {
+                    tracing::error!("invalid sync interval: {}", e);
+                    std::process::exit(1);
+                })),
+                _ => None,
+            };
+            let schedules = match cli.command {
+                Some(Commands::Serve { sync_scheduled: true, .. }) => Some(require_schedules(&config)),
+                _ => None,
+            };
 
-            let port = std::env::var("PORT")
-                .unwrap_or_else(|_| "3000".to_string())
-                .parse::<u16>()
-                .expect("PORT must be a valid number");
+            tracing::info!("connecting to database");
+            let db_pool = connect_db(&config.database);
+            let shutdown = Shutdown::listen();
+            let jobs = SyncJobs::spawn(db_pool.clone(), DataRepo::new(&config.data_repo), shutdown.clone());
+            let router = create_app_router(&config, db_pool.clone(), jobs.clone()).await;
 
-            let addr = SocketAddr::from(([0, 0, 0, 0], port));
+            let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
             tracing::info!("starting server on {}", addr);
 
+            let repo = DataRepo::new(&config.data_repo);
+            let scheduler = match (sync_every, schedules) {
+                (Some(interval), _) => Some(service::sync::spawn_sync_scheduler(db_pool, repo, interval, shutdown.clone())),
+                (None, Some(schedules)) => Some(service::schedule::spawn_scheduled_sync(
+                    db_pool,
+                    repo,
+                    schedules,
+                    shutdown.clone(),
+                )),
+                (None, None) => None,
+            };
+
             let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-            axum::serve(listener, router).await.unwrap();
+            let server = axum::serve(listener, router.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown({
+                    let shutdown = shutdown.clone();
+                    async move { shutdown.requested().await }
+                });
+
+            // Stop accepting connections on a signal, then give in-flight
+            // requests (e.g. long streamed responses) a bounded time to finish
+            let timeout = config.server.shutdown_timeout();
+            tokio::select! {
+                result = server.into_future() => result.unwrap(),
+                _ = async {
+                    shutdown.requested().await;
+                    tokio::time::sleep(timeout).await;
+                } => {
+                    tracing::warn!("requests still in flight after {}s, exiting", timeout.as_secs());
+                }
+            }
+            tracing::info!("server stopped");
+
+            // A running sync stops after the month it is storing; queued
+            // admin jobs are failed
+            let syncs = async {
+                if let Some(scheduler) = scheduler {
+                    let _ = scheduler.await;
+                }
+                jobs.stopped().await;
+            };
+            if tokio::time::timeout(timeout, syncs).await.is_err() {
+                tracing::warn!("sync still running after {}s, exiting", timeout.as_secs());
+            }
+        }
+        Some(Commands::Sync { scheduled: true, .. }) => {
+            let schedules = require_schedules(&config);
+            let db_pool = connect_db(&config.database);
+            let repo = DataRepo::new(&config.data_repo);
+            let shutdown = Shutdown::listen();
+            if let Err(e) = service::schedule::spawn_scheduled_sync(db_pool, repo, schedules, shutdown).await {
+                tracing::error!("scheduled sync failed: {}", e);
+                std::process::exit(1);
+            }
         }
         Some(Commands::Sync {
             ref country,
+            ref zone,
+            from,
+            to,
+            force,
             ref r#loop,
+            wait,
+            skip_if_locked,
+            dry_run,
+            json,
+            ..
         }) => {
-            let db_pool = connect_db();
+            if let (Some(from), Some(to)) = (from, to)
+                && from > to
+            {
+                tracing::error!("--from must not be after --to");
+                std::process::exit(1);
+            }
+
+            let db_pool = connect_db(&config.database);
             let mut conn = db_pool.get().unwrap();
+            let repo = DataRepo::new(&config.data_repo);
+            let shutdown = Shutdown::listen();
+            let options = SyncOptions {
+                wait_for_lock: wait,
+                zone: zone.clone(),
+                from,
+                to,
+                force,
+            };
+
+            // A zone belongs to exactly one country
+            let country = match (country, zone) {
+                (None, Some(zone)) => match select_zone_by_code(&mut conn, zone) {
+                    Ok(Some(zone)) => Some(zone.country),
+                    Ok(None) => {
+                        tracing::error!("unknown zone {}; pass --country to sync a zone not stored yet", zone);
+                        std::process::exit(1);
+                    }
+                    Err(e) => {
+                        tracing::error!("failed to look up zone {}: {}", zone, e);
+                        std::process::exit(1);
+                    }
+                },
+                _ => country.clone(),
+            };
+            let country = &country;
+
+            if dry_run {
+                let diff = service::dry_run::dry_run(&mut conn, &repo, country.as_deref(), &options, &shutdown).await;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&diff).unwrap());
+                } else {
+                    print_diff(&diff, force);
+                }
+                return;
+            }
 
             match r#loop {
                 Some(interval_str) => {
@@ -101,15 +494,54 @@ async fn main() {
                     });
                     tracing::info!("running sync in loop mode (interval: {}s)", interval.as_secs());
                     loop {
-                        run_sync(country, &mut conn).await;
+                        run_sync(country, &mut conn, &repo, &options, &shutdown).await;
+                        if shutdown.is_requested() {
+                            break;
+                        }
                         tracing::info!("sleeping for {}s until next sync...", interval.as_secs());
-                        tokio::time::sleep(interval).await;
+                        tokio::select! {
+                            _ = tokio::time::sleep(interval) => {}
+                            _ = shutdown.requested() => break,
+                        }
                     }
+                    tracing::info!("sync loop stopped");
                 }
                 None => {
-                    run_sync(country, &mut conn).await;
+                    let skipped = run_sync(country, &mut conn, &repo, &options, &shutdown).await;
+                    if !skipped.is_empty() && !skip_if_locked && !shutdown.is_requested() {
+                        tracing::error!(
+                            "not synced, another process is syncing: {} (use --wait or --skip-if-locked)",
+                            skipped.join(", ")
+                        );
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        Some(Commands::Schedule) => {
+            let db_pool = connect_db(&config.database);
+            let mut conn = db_pool.get().unwrap();
+            print_schedule(&mut conn, &config.sync.schedules);
+        }
+        Some(Commands::Keys { command }) => {
+            let db_pool = connect_db(&config.database);
+            let mut conn = db_pool.get().unwrap();
+            run_keys(command, &mut conn);
+        }
+        Some(Commands::Config {
+            command: ConfigCommand::Print,
+        }) => {
+            match toml::to_string_pretty(&config.redacted()) {
+                Ok(text) => print!("{}", text),
+                Err(e) => {
+                    tracing::error!("failed to render configuration: {}", e);
+                    std::process::exit(1);
                 }
             }
+            if let Err(e) = config.validate() {
+                tracing::error!("{}", e);
+                std::process::exit(1);
+            }
         }
     }
 }
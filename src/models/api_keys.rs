@@ -0,0 +1,114 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::api_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    pub key_prefix: String,
+    pub key_hash: String,
+    pub requests_per_minute: i32,
+    pub daily_quota: Option<i64>,
+    pub created_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+    /// Allowed to use the `/admin` endpoints
+    pub admin: bool,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::api_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewApiKey {
+    pub name: String,
+    pub key_prefix: String,
+    pub key_hash: String,
+    pub requests_per_minute: i32,
+    pub daily_quota: Option<i64>,
+    pub admin: bool,
+}
+
+pub fn insert_api_key(conn: &mut PgConnection, key: &NewApiKey) -> Result<ApiKey, diesel::result::Error> {
+    use crate::schema::api_keys;
+
+    diesel::insert_into(api_keys::table)
+        .values(key)
+        .returning(ApiKey::as_returning())
+        .get_result(conn)
+}
+
+pub fn select_api_keys(conn: &mut PgConnection) -> Result<Vec<ApiKey>, diesel::result::Error> {
+    use crate::schema::api_keys;
+
+    api_keys::table
+        .select(ApiKey::as_select())
+        .order(api_keys::id.asc())
+        .load(conn)
+}
+
+/// Active (not revoked) key with the given hash.
+pub fn select_active_api_key_by_hash(
+    conn: &mut PgConnection,
+    key_hash: &str,
+) -> Result<Option<ApiKey>, diesel::result::Error> {
+    use crate::schema::api_keys;
+
+    api_keys::table
+        .filter(api_keys::key_hash.eq(key_hash))
+        .filter(api_keys::revoked_at.is_null())
+        .select(ApiKey::as_select())
+        .first(conn)
+        .optional()
+}
+
+/// Marks a key as revoked; returns false if it does not exist or already was.
+pub fn revoke_api_key(conn: &mut PgConnection, id: i64) -> Result<bool, diesel::result::Error> {
+    use crate::schema::api_keys;
+
+    let updated = diesel::update(api_keys::table)
+        .filter(api_keys::id.eq(id))
+        .filter(api_keys::revoked_at.is_null())
+        .set(api_keys::revoked_at.eq(diesel::dsl::now))
+        .execute(conn)?;
+    Ok(updated > 0)
+}
+
+/// Requests recorded for a key on the given (UTC) day.
+pub fn select_api_key_usage(
+    conn: &mut PgConnection,
+    api_key_id: i64,
+    day: NaiveDate,
+) -> Result<i64, diesel::result::Error> {
+    use crate::schema::api_key_usage;
+
+    api_key_usage::table
+        .filter(api_key_usage::api_key_id.eq(api_key_id))
+        .filter(api_key_usage::day.eq(day))
+        .select(api_key_usage::requests)
+        .first(conn)
+        .optional()
+        .map(|requests| requests.unwrap_or(0))
+}
+
+/// Adds `requests` to a key's counter for the given day.
+pub fn add_api_key_usage(
+    conn: &mut PgConnection,
+    api_key_id: i64,
+    day: NaiveDate,
+    requests: i64,
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::api_key_usage;
+
+    diesel::insert_into(api_key_usage::table)
+        .values((
+            api_key_usage::api_key_id.eq(api_key_id),
+            api_key_usage::day.eq(day),
+            api_key_usage::requests.eq(requests),
+        ))
+        .on_conflict((api_key_usage::api_key_id, api_key_usage::day))
+        .do_update()
+        .set(api_key_usage::requests.eq(api_key_usage::requests + requests))
+        .execute(conn)?;
+    Ok(())
+}
@@ -1,6 +1,6 @@
-use diesel::prelude::*;
+use diesel::{dsl, prelude::*, upsert::excluded};
 
-#[derive(Queryable, Selectable, Insertable, AsChangeset)]
+#[derive(Clone, Queryable, Selectable, Insertable, AsChangeset)]
 #[diesel(table_name = crate::schema::countries)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct UpsertCountry {
@@ -25,14 +25,27 @@ impl From<&crate::api::data_repo::Country> for UpsertCountry {
     }
 }
 
+/// Inserts or updates a country. A stored country is only written, and gets a
+/// new `updated_at`, when something differs.
 pub fn upsert_country(conn: &mut PgConnection, country: UpsertCountry) -> Result<(), diesel::result::Error> {
+    // `ON CONFLICT ... DO UPDATE ... WHERE`
+    use diesel::query_dsl::methods::FilterDsl;
+
     use crate::schema::countries;
 
     diesel::insert_into(countries::table)
         .values(&country)
         .on_conflict(countries::code)
         .do_update()
-        .set(&country)
+        .set((&country, countries::updated_at.eq(dsl::now)))
+        .filter(
+            countries::name
+                .is_distinct_from(excluded(countries::name))
+                .or(countries::source.is_distinct_from(excluded(countries::source)))
+                .or(countries::geojson.is_distinct_from(excluded(countries::geojson)))
+                .or(countries::mapping.is_distinct_from(excluded(countries::mapping)))
+                .or(countries::shape_property.is_distinct_from(excluded(countries::shape_property))),
+        )
         .execute(conn)?;
     Ok(())
 }
@@ -45,3 +58,24 @@ pub fn select_countries(conn: &mut PgConnection) -> Result<Vec<UpsertCountry>, d
         .order(countries::code.asc())
         .load(conn)
 }
+
+pub fn select_country_by_code(conn: &mut PgConnection, code: &str) -> Result<Option<UpsertCountry>, diesel::result::Error> {
+    use crate::schema::countries;
+
+    countries::table
+        .filter(countries::code.eq(code))
+        .select(UpsertCountry::as_select())
+        .first(conn)
+        .optional()
+}
+
+/// Newest `updated_at` among countries.
+pub fn select_countries_last_modified(
+    conn: &mut PgConnection,
+) -> Result<Option<chrono::NaiveDateTime>, diesel::result::Error> {
+    use crate::schema::countries;
+
+    countries::table
+        .select(dsl::max(countries::updated_at))
+        .first(conn)
+}
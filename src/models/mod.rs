@@ -1,4 +1,6 @@
+pub mod api_keys;
 pub mod countries;
 pub mod db;
 pub mod prayer_times;
+pub mod sync_runs;
 pub mod zones;
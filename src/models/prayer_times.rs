@@ -1,7 +1,11 @@
-use chrono::{NaiveDate, NaiveTime};
-use diesel::prelude::*;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use diesel::{
+    dsl,
+    prelude::*,
+    upsert::excluded,
+};
 
-#[derive(Queryable, Selectable)]
+#[derive(Clone, Queryable, Selectable)]
 #[diesel(table_name = crate::schema::prayer_times)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct SelectPrayerTime {
@@ -15,6 +19,10 @@ pub struct SelectPrayerTime {
     pub asr: NaiveTime,
     pub maghrib: NaiveTime,
     pub isha: NaiveTime,
+    pub created_at: NaiveDateTime,
+    /// When the times were last written; equals `created_at` until a
+    /// forced sync overwrites them
+    pub updated_at: NaiveDateTime,
 }
 
 #[derive(Insertable, AsChangeset)]
@@ -62,18 +70,59 @@ pub fn select_last_prayer_time_for_zone(
         .optional()
 }
 
+/// Inserts the rows in a single statement, so a batch (one month during
+/// sync) is stored whole or not at all. Days already stored are kept.
+/// Returns the number of rows inserted.
 pub fn upsert_prayer_times(
     conn: &mut PgConnection,
     prayer_times: &[UpsertPrayerTime],
-) -> Result<(), diesel::result::Error> {
+) -> Result<usize, diesel::result::Error> {
     use crate::schema::prayer_times;
 
     diesel::insert_into(prayer_times::table)
         .values(prayer_times)
         .on_conflict((prayer_times::zone_code, prayer_times::date))
         .do_nothing()
-        .execute(conn)?;
-    Ok(())
+        .execute(conn)
+}
+
+/// Like [`upsert_prayer_times`], but stored days whose times differ are
+/// overwritten and get a new `updated_at`, so their cached versions change.
+/// Returns the number of rows inserted or changed.
+pub fn overwrite_prayer_times(
+    conn: &mut PgConnection,
+    prayer_times: &[UpsertPrayerTime],
+) -> Result<usize, diesel::result::Error> {
+    // `ON CONFLICT ... DO UPDATE ... WHERE`
+    use diesel::query_dsl::methods::FilterDsl;
+
+    use crate::schema::prayer_times;
+
+    diesel::insert_into(prayer_times::table)
+        .values(prayer_times)
+        .on_conflict((prayer_times::zone_code, prayer_times::date))
+        .do_update()
+        .set((
+            prayer_times::imsak.eq(excluded(prayer_times::imsak)),
+            prayer_times::fajr.eq(excluded(prayer_times::fajr)),
+            prayer_times::syuruk.eq(excluded(prayer_times::syuruk)),
+            prayer_times::dhuhr.eq(excluded(prayer_times::dhuhr)),
+            prayer_times::asr.eq(excluded(prayer_times::asr)),
+            prayer_times::maghrib.eq(excluded(prayer_times::maghrib)),
+            prayer_times::isha.eq(excluded(prayer_times::isha)),
+            prayer_times::updated_at.eq(dsl::now),
+        ))
+        .filter(
+            prayer_times::imsak
+                .is_distinct_from(excluded(prayer_times::imsak))
+                .or(prayer_times::fajr.is_distinct_from(excluded(prayer_times::fajr)))
+                .or(prayer_times::syuruk.is_distinct_from(excluded(prayer_times::syuruk)))
+                .or(prayer_times::dhuhr.is_distinct_from(excluded(prayer_times::dhuhr)))
+                .or(prayer_times::asr.is_distinct_from(excluded(prayer_times::asr)))
+                .or(prayer_times::maghrib.is_distinct_from(excluded(prayer_times::maghrib)))
+                .or(prayer_times::isha.is_distinct_from(excluded(prayer_times::isha))),
+        )
+        .execute(conn)
 }
 
 pub fn select_prayer_times_for_zone(
@@ -93,6 +142,153 @@ pub fn select_prayer_times_for_zone(
         .load(conn)
 }
 
+/// Up to `limit` rows of a zone from `from` through `to`, in date order.
+/// Long ranges are read page by page, each page starting the day after the
+/// last one ended.
+pub fn select_prayer_times_page(
+    conn: &mut PgConnection,
+    zone_code: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+    limit: i64,
+) -> Result<Vec<SelectPrayerTime>, diesel::result::Error> {
+    use crate::schema::prayer_times;
+
+    prayer_times::table
+        .filter(prayer_times::zone_code.eq(zone_code))
+        .filter(prayer_times::date.ge(from))
+        .filter(prayer_times::date.le(to))
+        .select(SelectPrayerTime::as_select())
+        .order(prayer_times::date.asc())
+        .limit(limit)
+        .load(conn)
+}
+
+/// Row count and newest `updated_at` for a zone's date range. Rows are only
+/// inserted, or overwritten with a new `updated_at` (see
+/// [`overwrite_prayer_times`]), so this identifies the data version without
+/// loading it.
+pub fn select_prayer_times_version(
+    conn: &mut PgConnection,
+    zone_code: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<(i64, Option<NaiveDateTime>), diesel::result::Error> {
+    use crate::schema::prayer_times;
+
+    prayer_times::table
+        .filter(prayer_times::zone_code.eq(zone_code))
+        .filter(prayer_times::date.ge(from))
+        .filter(prayer_times::date.le(to))
+        .select((dsl::count_star(), dsl::max(prayer_times::updated_at)))
+        .first(conn)
+}
+
+/// First and last stored date for a zone, `None` when it has no rows.
+pub fn select_date_range_for_zone(
+    conn: &mut PgConnection,
+    zone_code: &str,
+) -> Result<(Option<NaiveDate>, Option<NaiveDate>), diesel::result::Error> {
+    use crate::schema::prayer_times;
+
+    prayer_times::table
+        .filter(prayer_times::zone_code.eq(zone_code))
+        .select((dsl::min(prayer_times::date), dsl::max(prayer_times::date)))
+        .first(conn)
+}
+
+/// First and last stored date across all zones of a country.
+pub fn select_date_range_for_country(
+    conn: &mut PgConnection,
+    country: &str,
+) -> Result<(Option<NaiveDate>, Option<NaiveDate>), diesel::result::Error> {
+    use crate::schema::{prayer_times, zones};
+
+    let country_zones = zones::table
+        .filter(zones::country.eq(country))
+        .select(zones::zone_code);
+
+    prayer_times::table
+        .filter(prayer_times::zone_code.eq_any(country_zones))
+        .select((dsl::min(prayer_times::date), dsl::max(prayer_times::date)))
+        .first(conn)
+}
+
+/// Per-zone aggregate of stored prayer times. Zones without any rows have
+/// `None` dates and a zero count.
+#[derive(QueryableByName)]
+pub struct ZoneCoverageRow {
+    #[diesel(sql_type = diesel::sql_types::Varchar)]
+    pub zone_code: String,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Date>)]
+    pub first_date: Option<NaiveDate>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Date>)]
+    pub last_date: Option<NaiveDate>,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub total_days: i64,
+}
+
+/// Inclusive run of dates missing between two stored dates of a zone.
+#[derive(QueryableByName)]
+pub struct CoverageGapRow {
+    #[diesel(sql_type = diesel::sql_types::Varchar)]
+    pub zone_code: String,
+    #[diesel(sql_type = diesel::sql_types::Date)]
+    pub gap_start: NaiveDate,
+    #[diesel(sql_type = diesel::sql_types::Date)]
+    pub gap_end: NaiveDate,
+}
+
+/// Coverage for zones, optionally limited to a country and/or a single zone.
+pub fn select_coverage(
+    conn: &mut PgConnection,
+    country: Option<&str>,
+    zone_code: Option<&str>,
+) -> Result<Vec<ZoneCoverageRow>, diesel::result::Error> {
+    use diesel::sql_types::{Nullable, Varchar};
+
+    diesel::sql_query(
+        "SELECT z.zone_code, MIN(p.date) AS first_date, MAX(p.date) AS last_date, \
+                COUNT(p.date) AS total_days \
+         FROM zones z \
+         LEFT JOIN prayer_times p ON p.zone_code = z.zone_code \
+         WHERE ($1::varchar IS NULL OR z.country = $1) \
+           AND ($2::varchar IS NULL OR z.zone_code = $2) \
+         GROUP BY z.zone_code \
+         ORDER BY z.zone_code",
+    )
+    .bind::<Nullable<Varchar>, _>(country)
+    .bind::<Nullable<Varchar>, _>(zone_code)
+    .load(conn)
+}
+
+/// Missing date ranges between the first and last stored date of each zone,
+/// found by comparing every row with its predecessor.
+pub fn select_coverage_gaps(
+    conn: &mut PgConnection,
+    country: Option<&str>,
+    zone_code: Option<&str>,
+) -> Result<Vec<CoverageGapRow>, diesel::result::Error> {
+    use diesel::sql_types::{Nullable, Varchar};
+
+    diesel::sql_query(
+        "SELECT zone_code, prev_date + 1 AS gap_start, date - 1 AS gap_end \
+         FROM ( \
+             SELECT p.zone_code, p.date, \
+                    LAG(p.date) OVER (PARTITION BY p.zone_code ORDER BY p.date) AS prev_date \
+             FROM prayer_times p \
+             JOIN zones z ON z.zone_code = p.zone_code \
+             WHERE ($1::varchar IS NULL OR z.country = $1) \
+               AND ($2::varchar IS NULL OR z.zone_code = $2) \
+         ) t \
+         WHERE date - prev_date > 1 \
+         ORDER BY zone_code, gap_start",
+    )
+    .bind::<Nullable<Varchar>, _>(country)
+    .bind::<Nullable<Varchar>, _>(zone_code)
+    .load(conn)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,33 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+/// Last completed sync of a country.
+#[derive(Debug, Clone, Queryable, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = crate::schema::sync_runs)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SyncRun {
+    pub country: String,
+    pub started_at: NaiveDateTime,
+    pub finished_at: NaiveDateTime,
+}
+
+pub fn select_sync_runs(conn: &mut PgConnection) -> Result<Vec<SyncRun>, diesel::result::Error> {
+    use crate::schema::sync_runs;
+
+    sync_runs::table
+        .select(SyncRun::as_select())
+        .order(sync_runs::country.asc())
+        .load(conn)
+}
+
+pub fn upsert_sync_run(conn: &mut PgConnection, run: &SyncRun) -> Result<(), diesel::result::Error> {
+    use crate::schema::sync_runs;
+
+    diesel::insert_into(sync_runs::table)
+        .values(run)
+        .on_conflict(sync_runs::country)
+        .do_update()
+        .set(run)
+        .execute(conn)?;
+    Ok(())
+}
@@ -1,6 +1,6 @@
-use diesel::prelude::*;
+use diesel::{dsl, prelude::*, upsert::excluded};
 
-#[derive(Queryable, Selectable, Insertable, AsChangeset)]
+#[derive(Clone, Queryable, Selectable, Insertable, AsChangeset)]
 #[diesel(table_name = crate::schema::zones)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct UpsertZone {
@@ -9,6 +9,27 @@ pub struct UpsertZone {
     pub state: String,
     pub location: String,
     pub timezone: String,
+    /// Reference point (e.g. the zone centroid); only written by upsert when
+    /// the zone is new.
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub min_latitude: Option<f64>,
+    pub min_longitude: Option<f64>,
+    pub max_latitude: Option<f64>,
+    pub max_longitude: Option<f64>,
+}
+
+/// Centroid and bounding box derived from the country GeoJSON.
+#[derive(AsChangeset)]
+#[diesel(table_name = crate::schema::zones)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UpdateZoneGeometry {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub min_latitude: f64,
+    pub min_longitude: f64,
+    pub max_latitude: f64,
+    pub max_longitude: f64,
 }
 
 impl UpsertZone {
@@ -26,22 +47,71 @@ impl From<&crate::api::data_repo::Zone> for UpsertZone {
             state: z.state.clone(),
             location: z.location.clone(),
             timezone: z.timezone.clone(),
+            latitude: None,
+            longitude: None,
+            min_latitude: None,
+            min_longitude: None,
+            max_latitude: None,
+            max_longitude: None,
         }
     }
 }
 
+/// Inserts or updates a zone. A stored zone is only written, and gets a new
+/// `updated_at`, when its details differ; its geometry is left to
+/// [`update_zone_geometry`].
 pub fn upsert_zone(conn: &mut PgConnection, zone: UpsertZone) -> Result<(), diesel::result::Error> {
+    // `ON CONFLICT ... DO UPDATE ... WHERE`
+    use diesel::query_dsl::methods::FilterDsl;
+
     use crate::schema::zones;
 
     diesel::insert_into(zones::table)
         .values(&zone)
         .on_conflict(zones::zone_code)
         .do_update()
-        .set(&zone)
+        .set((
+            zones::country.eq(excluded(zones::country)),
+            zones::state.eq(excluded(zones::state)),
+            zones::location.eq(excluded(zones::location)),
+            zones::timezone.eq(excluded(zones::timezone)),
+            zones::updated_at.eq(dsl::now),
+        ))
+        .filter(
+            zones::country
+                .is_distinct_from(excluded(zones::country))
+                .or(zones::state.is_distinct_from(excluded(zones::state)))
+                .or(zones::location.is_distinct_from(excluded(zones::location)))
+                .or(zones::timezone.is_distinct_from(excluded(zones::timezone))),
+        )
         .execute(conn)?;
     Ok(())
 }
 
+/// Stores a zone's geometry, with a new `updated_at` when it differs.
+pub fn update_zone_geometry(
+    conn: &mut PgConnection,
+    zone_code: &str,
+    geometry: &UpdateZoneGeometry,
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::zones;
+
+    diesel::update(
+        zones::table.find(zone_code).filter(
+            zones::latitude
+                .is_distinct_from(geometry.latitude)
+                .or(zones::longitude.is_distinct_from(geometry.longitude))
+                .or(zones::min_latitude.is_distinct_from(geometry.min_latitude))
+                .or(zones::min_longitude.is_distinct_from(geometry.min_longitude))
+                .or(zones::max_latitude.is_distinct_from(geometry.max_latitude))
+                .or(zones::max_longitude.is_distinct_from(geometry.max_longitude)),
+        ),
+    )
+    .set((geometry, zones::updated_at.eq(dsl::now)))
+    .execute(conn)?;
+    Ok(())
+}
+
 pub fn select_zones(conn: &mut PgConnection) -> Result<Vec<UpsertZone>, diesel::result::Error> {
     use crate::schema::zones;
 
@@ -70,3 +140,26 @@ pub fn select_zones_by_country(conn: &mut PgConnection, country: &str) -> Result
         .order(zones::zone_code.asc())
         .load(conn)
 }
+
+pub fn count_zones_by_country(conn: &mut PgConnection, country: &str) -> Result<i64, diesel::result::Error> {
+    use crate::schema::zones;
+
+    zones::table
+        .filter(zones::country.eq(country))
+        .count()
+        .get_result(conn)
+}
+
+/// Newest `updated_at` among zones, optionally limited to a country.
+pub fn select_zones_last_modified(
+    conn: &mut PgConnection,
+    country: Option<&str>,
+) -> Result<Option<chrono::NaiveDateTime>, diesel::result::Error> {
+    use crate::schema::zones;
+
+    let mut query = zones::table.select(dsl::max(zones::updated_at)).into_boxed();
+    if let Some(country) = country {
+        query = query.filter(zones::country.eq(country));
+    }
+    query.first(conn)
+}
@@ -0,0 +1,80 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{
+    models::api_keys::select_active_api_key_by_hash,
+    routes::{AppError, AppState},
+    service::api_keys::hash_key,
+};
+
+const X_API_KEY: &str = "x-api-key";
+
+/// API key from `X-API-Key` or `Authorization: Bearer <key>`.
+pub fn api_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(key) = headers.get(X_API_KEY).and_then(|v| v.to_str().ok()) {
+        return Some(key.trim());
+    }
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Address of the connected peer (unspecified when not served over TCP).
+pub fn peer_ip(request: &Request) -> IpAddr {
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+}
+
+/// Middleware admitting only requests with an active admin key. The key is
+/// looked up on every request, so a revocation takes effect immediately.
+pub async fn require_admin(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(key) = api_key(request.headers()) else {
+        return AppError::Unauthorized("Admin API key required".to_string()).into_response();
+    };
+    let key = state
+        .db_pool
+        .get()
+        .map_err(AppError::from)
+        .and_then(|mut conn| Ok(select_active_api_key_by_hash(&mut conn, &hash_key(key))?));
+
+    match key {
+        Ok(Some(key)) if key.admin => next.run(request).await,
+        Ok(Some(_)) => AppError::Forbidden("API key is not an admin key".to_string()).into_response(),
+        Ok(None) => AppError::Unauthorized("Invalid or revoked API key".to_string()).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_api_key_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(api_key(&headers), None);
+
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer ss_abc"));
+        assert_eq!(api_key(&headers), Some("ss_abc"));
+
+        headers.insert(X_API_KEY, HeaderValue::from_static("ss_def"));
+        assert_eq!(api_key(&headers), Some("ss_def"));
+
+        headers.remove(X_API_KEY);
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Basic dXNlcg=="));
+        assert_eq!(api_key(&headers), None);
+    }
+}
@@ -0,0 +1,139 @@
+use axum::{
+    Json,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    models::{countries::select_country_by_code, zones::select_zone_by_code},
+    routes::{AppError, AppState, ErrorResponse, extract::Path},
+    service::jobs::{QueueFull, SyncJob, SyncTarget},
+};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncJobResponse {
+    pub data: SyncJob,
+}
+
+/// Seconds admins are asked to wait when the sync queue is full.
+const QUEUE_FULL_RETRY_SECS: u64 = 60;
+
+impl From<QueueFull> for AppError {
+    fn from(_: QueueFull) -> Self {
+        AppError::RateLimited(QUEUE_FULL_RETRY_SECS)
+    }
+}
+
+/// 202 with the queued job, and its status URL in `Location`.
+fn accepted(job: SyncJob) -> Response {
+    let location = format!("/admin/jobs/{}", job.id);
+    (
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(SyncJobResponse { data: job }),
+    )
+        .into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/sync",
+    tag = "admin",
+    security(("api_key" = []), ("bearer" = [])),
+    responses(
+        (status = 202, description = "Sync of all countries queued, or already waiting", body = SyncJobResponse),
+        (status = 401, description = "Missing, invalid or revoked API key", body = ErrorResponse),
+        (status = 403, description = "Not an admin key", body = ErrorResponse),
+        (status = 429, description = "Too many syncs queued", body = ErrorResponse),
+    )
+)]
+pub async fn post_sync_all(State(state): State<AppState>) -> Result<Response, AppError> {
+    Ok(accepted(state.jobs.enqueue(SyncTarget::All)?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/sync/{country}",
+    tag = "admin",
+    security(("api_key" = []), ("bearer" = [])),
+    params(("country" = String, Path, description = "Country code, e.g. `MY`")),
+    responses(
+        (status = 202, description = "Sync of the country queued, or already waiting", body = SyncJobResponse),
+        (status = 401, description = "Missing, invalid or revoked API key", body = ErrorResponse),
+        (status = 403, description = "Not an admin key", body = ErrorResponse),
+        (status = 404, description = "Unknown country", body = ErrorResponse),
+        (status = 429, description = "Too many syncs queued", body = ErrorResponse),
+    )
+)]
+pub async fn post_sync_country(
+    Path(country): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let mut conn = state.db_pool.get()?;
+    let country = select_country_by_code(&mut conn, &country.to_uppercase())?.ok_or_else(|| AppError::NotFound(
+        format!("Country '{}' not found", country),
+    ))?;
+
+    Ok(accepted(state.jobs.enqueue(SyncTarget::Country { country: country.code })?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/sync/{country}/{zone}",
+    tag = "admin",
+    security(("api_key" = []), ("bearer" = [])),
+    params(
+        ("country" = String, Path, description = "Country code, e.g. `MY`"),
+        ("zone" = String, Path, description = "Zone code in that country, e.g. `SGR01`"),
+    ),
+    responses(
+        (status = 202, description = "Sync of the zone queued, or already waiting", body = SyncJobResponse),
+        (status = 401, description = "Missing, invalid or revoked API key", body = ErrorResponse),
+        (status = 403, description = "Not an admin key", body = ErrorResponse),
+        (status = 404, description = "Unknown zone, or not in that country", body = ErrorResponse),
+        (status = 429, description = "Too many syncs queued", body = ErrorResponse),
+    )
+)]
+pub async fn post_sync_zone(
+    Path((country, zone)): Path<(String, String)>,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let mut conn = state.db_pool.get()?;
+    let zone_info = select_zone_by_code(&mut conn, &zone.to_uppercase())?
+        .filter(|z| z.country.eq_ignore_ascii_case(&country))
+        .ok_or_else(|| AppError::NotFound(
+            format!("Zone '{}' not found in country '{}'", zone, country),
+        ))?;
+
+    Ok(accepted(state.jobs.enqueue(SyncTarget::Zone {
+        country: zone_info.country,
+        zone: zone_info.zone_code,
+    })?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/jobs/{id}",
+    tag = "admin",
+    security(("api_key" = []), ("bearer" = [])),
+    params(("id" = Uuid, Path, description = "Job id returned when the sync was queued")),
+    responses(
+        (status = 200, description = "Job status, progress and errors", body = SyncJobResponse),
+        (status = 401, description = "Missing, invalid or revoked API key", body = ErrorResponse),
+        (status = 403, description = "Not an admin key", body = ErrorResponse),
+        (status = 404, description = "Unknown job, or finished long ago", body = ErrorResponse),
+    )
+)]
+pub async fn get_job(
+    Path(id): Path<Uuid>,
+    State(state): State<AppState>,
+) -> Result<Json<SyncJobResponse>, AppError> {
+    let job = state.jobs.get(id).ok_or_else(|| AppError::NotFound(
+        format!("Job '{}' not found", id),
+    ))?;
+    Ok(Json(SyncJobResponse { data: job }))
+}
@@ -0,0 +1,199 @@
+use axum::{
+    body::Body,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::routes::AppError;
+
+/// Prayer times for a given day are fixed once published.
+pub const PRAYER_TIMES_CACHE_CONTROL: &str = "public, max-age=86400";
+/// Longest a response relative to the current date is cached.
+const MAX_RELATIVE_AGE: i64 = 86400;
+/// Zone and country lists only change on sync.
+pub const LIST_CACHE_CONTROL: &str = "public, max-age=3600";
+
+pub(crate) const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Strong entity tag: the first 128 bits of a SHA-256 over the given parts.
+pub fn etag_for(parts: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part);
+    }
+    let hex: String = hasher.finalize()[..16]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("\"{}\"", hex)
+}
+
+/// Cache policy for a response that depends on the current date in `tz`
+/// (e.g. `from=today`): it changes at the next local midnight, so shared
+/// caches keep it until then only.
+pub fn until_local_midnight(tz: chrono_tz::Tz, now: DateTime<Utc>) -> String {
+    let seconds = now
+        .with_timezone(&tz)
+        .date_naive()
+        .succ_opt()
+        .and_then(|tomorrow| tomorrow.and_hms_opt(0, 0, 0)?.and_local_timezone(tz).earliest())
+        .map_or(0, |midnight| (midnight.with_timezone(&Utc) - now).num_seconds());
+    format!("public, max-age={}", seconds.clamp(0, MAX_RELATIVE_AGE))
+}
+
+/// Validators sent with a response and checked against conditional requests.
+/// `last_modified` is a UTC timestamp (the database `updated_at`).
+pub struct Validators {
+    pub etag: String,
+    pub last_modified: Option<NaiveDateTime>,
+}
+
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',').map(str::trim).any(|tag| {
+        // If-None-Match uses the weak comparison function
+        tag == "*" || tag.trim_start_matches("W/") == etag
+    })
+}
+
+impl Validators {
+    /// Evaluates `If-None-Match`, falling back to `If-Modified-Since` only
+    /// when the former is absent (RFC 9110 §13.2.2).
+    pub fn is_not_modified(&self, headers: &HeaderMap) -> bool {
+        if let Some(value) = headers.get(header::IF_NONE_MATCH) {
+            return value
+                .to_str()
+                .is_ok_and(|v| etag_matches(v, &self.etag));
+        }
+
+        let (Some(value), Some(last_modified)) =
+            (headers.get(header::IF_MODIFIED_SINCE), self.last_modified)
+        else {
+            return false;
+        };
+        value
+            .to_str()
+            .ok()
+            .and_then(|v| NaiveDateTime::parse_from_str(v, HTTP_DATE_FORMAT).ok())
+            // HTTP dates have second precision
+            .is_some_and(|since| last_modified - since < TimeDelta::seconds(1))
+    }
+
+    fn apply(&self, response: &mut Response, cache_control: &str) {
+        let headers = response.headers_mut();
+        if let Ok(cache_control) = HeaderValue::from_str(cache_control) {
+            headers.insert(header::CACHE_CONTROL, cache_control);
+        }
+        if let Ok(etag) = HeaderValue::from_str(&self.etag) {
+            headers.insert(header::ETAG, etag);
+        }
+        if let Some(last_modified) = self.last_modified {
+            let value = last_modified.format(HTTP_DATE_FORMAT).to_string();
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.insert(header::LAST_MODIFIED, value);
+            }
+        }
+    }
+
+    /// Empty 304 response carrying the validators.
+    pub fn not_modified(&self, cache_control: &str) -> Response {
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        self.apply(&mut response, cache_control);
+        response
+    }
+
+    /// Attaches the validators and cache policy to a full response.
+    pub fn respond(&self, response: impl IntoResponse, cache_control: &str) -> Response {
+        let mut response = response.into_response();
+        self.apply(&mut response, cache_control);
+        response
+    }
+}
+
+/// Serializes `body` and answers with 304 when the client's copy is current.
+/// The ETag is derived from the serialized content and `last_modified`, the
+/// newest `updated_at` of the rows it was built from.
+pub fn cached_json<T: Serialize>(
+    headers: &HeaderMap,
+    body: &T,
+    last_modified: Option<NaiveDateTime>,
+    cache_control: &'static str,
+) -> Result<Response, AppError> {
+    let bytes = serde_json::to_vec(body)?;
+    let version = format!("{:?}", last_modified);
+    let validators = Validators {
+        etag: etag_for(&[&bytes, version.as_bytes()]),
+        last_modified,
+    };
+    if validators.is_not_modified(headers) {
+        return Ok(validators.not_modified(cache_control));
+    }
+
+    let response = (
+        [(header::CONTENT_TYPE, "application/json")],
+        Body::from(bytes),
+    );
+    Ok(validators.respond(response, cache_control))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validators() -> Validators {
+        Validators {
+            etag: etag_for(&[b"body"]),
+            last_modified: NaiveDateTime::parse_from_str("Mon, 19 Oct 2026 07:00:00 GMT", HTTP_DATE_FORMAT).ok(),
+        }
+    }
+
+    fn headers(name: header::HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_etag_is_stable_and_content_sensitive() {
+        assert_eq!(etag_for(&[b"a", b"bc"]), etag_for(&[b"a", b"bc"]));
+        assert_ne!(etag_for(&[b"a", b"bc"]), etag_for(&[b"ab", b"c"]));
+        assert_eq!(etag_for(&[b"x"]).len(), 34);
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let v = validators();
+        assert!(v.is_not_modified(&headers(header::IF_NONE_MATCH, &v.etag)));
+        assert!(v.is_not_modified(&headers(header::IF_NONE_MATCH, &format!("\"x\", W/{}", v.etag))));
+        assert!(v.is_not_modified(&headers(header::IF_NONE_MATCH, "*")));
+        assert!(!v.is_not_modified(&headers(header::IF_NONE_MATCH, "\"other\"")));
+        assert!(!v.is_not_modified(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn test_until_local_midnight() {
+        let tz = chrono_tz::Asia::Kuala_Lumpur;
+        // 23:00 in Kuala Lumpur (UTC+8)
+        let now = DateTime::parse_from_rfc3339("2026-10-19T15:00:00Z").unwrap().to_utc();
+        assert_eq!(until_local_midnight(tz, now), "public, max-age=3600");
+        let now = DateTime::parse_from_rfc3339("2026-10-19T16:00:00Z").unwrap().to_utc();
+        assert_eq!(until_local_midnight(tz, now), "public, max-age=86400");
+    }
+
+    #[test]
+    fn test_if_modified_since() {
+        let v = validators();
+        assert!(v.is_not_modified(&headers(header::IF_MODIFIED_SINCE, "Mon, 19 Oct 2026 07:00:00 GMT")));
+        assert!(v.is_not_modified(&headers(header::IF_MODIFIED_SINCE, "Tue, 20 Oct 2026 07:00:00 GMT")));
+        assert!(!v.is_not_modified(&headers(header::IF_MODIFIED_SINCE, "Sun, 18 Oct 2026 07:00:00 GMT")));
+        assert!(!v.is_not_modified(&headers(header::IF_MODIFIED_SINCE, "garbage")));
+
+        // If-None-Match takes precedence
+        let mut both = headers(header::IF_MODIFIED_SINCE, "Tue, 20 Oct 2026 07:00:00 GMT");
+        both.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"other\""));
+        assert!(!v.is_not_modified(&both));
+    }
+}
@@ -0,0 +1,273 @@
+use std::time::Duration;
+
+use axum::http::{HeaderName, HeaderValue, Method, header};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::StringList;
+
+/// Request headers browsers may send to the public API.
+const PUBLIC_ALLOWED_HEADERS: [HeaderName; 7] = [
+    header::ACCEPT,
+    header::AUTHORIZATION,
+    header::CONTENT_TYPE,
+    header::IF_MODIFIED_SINCE,
+    header::IF_NONE_MATCH,
+    HeaderName::from_static("x-api-key"),
+    HeaderName::from_static("x-request-id"),
+];
+
+/// Response headers scripts on other origins may read.
+const EXPOSED_HEADERS: [HeaderName; 11] = [
+    header::ETAG,
+    header::LAST_MODIFIED,
+    header::LINK,
+    header::RETRY_AFTER,
+    HeaderName::from_static("deprecation"),
+    HeaderName::from_static("sunset"),
+    HeaderName::from_static("x-request-id"),
+    HeaderName::from_static("ratelimit-limit"),
+    HeaderName::from_static("ratelimit-remaining"),
+    HeaderName::from_static("ratelimit-reset"),
+    HeaderName::from_static("ratelimit-policy"),
+];
+
+/// Origins allowed to make cross-origin requests.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AllowedOrigins {
+    Any,
+    List(Vec<HeaderValue>),
+}
+
+impl AllowedOrigins {
+    /// `*` for any origin, otherwise a comma-separated list. An empty list
+    /// allows no cross-origin requests.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if value.trim() == "*" {
+            return Ok(Self::Any);
+        }
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let origin = s.trim_end_matches('/');
+                if !(origin.starts_with("http://") || origin.starts_with("https://")) {
+                    return Err(format!("invalid origin: {}", s));
+                }
+                HeaderValue::from_str(origin).map_err(|_| format!("invalid origin: {}", s))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self::List)
+    }
+}
+
+/// CORS settings for one group of routes.
+#[derive(Debug, Clone)]
+pub struct CorsPolicy {
+    pub allowed_origins: AllowedOrigins,
+    pub allowed_methods: Vec<Method>,
+    pub allowed_headers: Vec<HeaderName>,
+    pub max_age: Duration,
+}
+
+impl CorsPolicy {
+    /// Read-only API open to every origin.
+    pub fn public() -> Self {
+        Self {
+            allowed_origins: AllowedOrigins::Any,
+            allowed_methods: vec![Method::GET, Method::HEAD],
+            allowed_headers: PUBLIC_ALLOWED_HEADERS.to_vec(),
+            max_age: Duration::from_secs(3600),
+        }
+    }
+
+    /// Operator endpoints: same-origin only unless origins are configured.
+    pub fn admin() -> Self {
+        Self {
+            allowed_origins: AllowedOrigins::List(Vec::new()),
+            allowed_methods: vec![Method::GET, Method::POST],
+            allowed_headers: vec![
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                HeaderName::from_static("x-api-key"),
+            ],
+            max_age: Duration::from_secs(600),
+        }
+    }
+
+    /// Applies the settings given in `overrides`, keeping the rest.
+    pub fn apply(&mut self, overrides: CorsOverrides) -> Result<(), String> {
+        if let Some(origins) = overrides.allowed_origins {
+            self.allowed_origins = AllowedOrigins::parse(&origins.joined())?;
+        }
+        if let Some(methods) = overrides.allowed_methods {
+            self.allowed_methods = parse_list(&methods.joined(), |s| s.to_uppercase().parse::<Method>().ok())
+                .map_err(|s| format!("invalid method: {}", s))?;
+        }
+        if let Some(headers) = overrides.allowed_headers {
+            self.allowed_headers = parse_list(&headers.joined(), |s| s.parse::<HeaderName>().ok())
+                .map_err(|s| format!("invalid header: {}", s))?;
+        }
+        if let Some(secs) = overrides.max_age_secs {
+            self.max_age = Duration::from_secs(secs);
+        }
+        Ok(())
+    }
+
+    pub fn layer(&self) -> CorsLayer {
+        let origins = match &self.allowed_origins {
+            AllowedOrigins::Any => AllowOrigin::any(),
+            AllowedOrigins::List(list) => AllowOrigin::list(list.clone()),
+        };
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(self.allowed_methods.clone())
+            .allow_headers(self.allowed_headers.clone())
+            .expose_headers(EXPOSED_HEADERS.to_vec())
+            .max_age(self.max_age)
+    }
+}
+
+impl Serialize for CorsPolicy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Effective<'a> {
+            allowed_origins: Vec<&'a str>,
+            allowed_methods: Vec<&'a str>,
+            allowed_headers: Vec<&'a str>,
+            max_age_secs: u64,
+        }
+
+        let allowed_origins = match &self.allowed_origins {
+            AllowedOrigins::Any => vec!["*"],
+            AllowedOrigins::List(list) => list.iter().filter_map(|o| o.to_str().ok()).collect(),
+        };
+        Effective {
+            allowed_origins,
+            allowed_methods: self.allowed_methods.iter().map(Method::as_str).collect(),
+            allowed_headers: self.allowed_headers.iter().map(HeaderName::as_str).collect(),
+            max_age_secs: self.max_age.as_secs(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Settings for one group as written in the config file or environment;
+/// anything left out keeps the group's default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsOverrides {
+    pub allowed_origins: Option<StringList>,
+    pub allowed_methods: Option<StringList>,
+    pub allowed_headers: Option<StringList>,
+    pub max_age_secs: Option<u64>,
+}
+
+/// CORS policies for the public and admin route groups.
+#[derive(Debug, Clone, Serialize)]
+pub struct CorsConfig {
+    pub public: CorsPolicy,
+    pub admin: CorsPolicy,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            public: CorsPolicy::public(),
+            admin: CorsPolicy::admin(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CorsConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Default, Deserialize)]
+        #[serde(default, deny_unknown_fields)]
+        struct Groups {
+            public: CorsOverrides,
+            admin: CorsOverrides,
+        }
+
+        let groups = Groups::deserialize(deserializer)?;
+        let mut config = Self::default();
+        config.public.apply(groups.public).map_err(serde::de::Error::custom)?;
+        config.admin.apply(groups.admin).map_err(serde::de::Error::custom)?;
+        Ok(config)
+    }
+}
+
+/// Parses a comma-separated list, returning the first invalid entry.
+fn parse_list<T>(value: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| parse(s).ok_or_else(|| s.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{Router, body::Body, http::Request, routing::post};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_allowed_origins() {
+        assert_eq!(AllowedOrigins::parse("*"), Ok(AllowedOrigins::Any));
+        assert_eq!(AllowedOrigins::parse(""), Ok(AllowedOrigins::List(Vec::new())));
+        assert_eq!(
+            AllowedOrigins::parse("https://a.example, http://localhost:8080/"),
+            Ok(AllowedOrigins::List(vec![
+                HeaderValue::from_static("https://a.example"),
+                HeaderValue::from_static("http://localhost:8080"),
+            ]))
+        );
+        assert!(AllowedOrigins::parse("a.example").is_err());
+    }
+
+    async fn preflight(policy: CorsPolicy, origin: &str) -> Option<HeaderValue> {
+        let app = Router::new()
+            .route("/admin/sync", post(|| async {}))
+            .layer(policy.layer());
+        let request = Request::options("/admin/sync")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).cloned()
+    }
+
+    #[tokio::test]
+    async fn test_admin_preflight_only_allows_listed_origins() {
+        assert_eq!(preflight(CorsPolicy::admin(), "https://partner.example").await, None);
+
+        let mut policy = CorsPolicy::admin();
+        policy
+            .apply(CorsOverrides {
+                allowed_origins: Some(StringList::from("https://ops.example")),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            preflight(policy.clone(), "https://ops.example").await,
+            Some(HeaderValue::from_static("https://ops.example"))
+        );
+        assert_eq!(preflight(policy, "https://partner.example").await, None);
+        assert_eq!(
+            preflight(CorsPolicy::public(), "https://partner.example").await,
+            Some(HeaderValue::from_static("*"))
+        );
+    }
+
+    #[test]
+    fn test_both_policies_allow_api_key_headers() {
+        for policy in [CorsPolicy::public(), CorsPolicy::admin()] {
+            assert!(policy.allowed_headers.contains(&header::AUTHORIZATION));
+            assert!(policy.allowed_headers.contains(&HeaderName::from_static("x-api-key")));
+        }
+    }
+}
@@ -1,12 +1,27 @@
-use axum::{Json, extract::State};
+use axum::{
+    Json,
+    extract::State,
+    http::HeaderMap,
+    response::Response,
+};
 use serde::Serialize;
+use utoipa::ToSchema;
 
 use crate::{
-    models::countries::{UpsertCountry, select_countries},
-    routes::{AppError, AppState},
+    models::{
+        countries::{UpsertCountry, select_country_by_code},
+        prayer_times::select_date_range_for_country,
+        zones::count_zones_by_country,
+    },
+    routes::{
+        AppError, AppState, ErrorResponse,
+        extract::Path,
+        caching::{LIST_CACHE_CONTROL, cached_json},
+        zones::Coverage,
+    },
 };
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct Country {
     pub code: String,
     pub name: String,
@@ -29,21 +44,78 @@ impl From<&UpsertCountry> for Country {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct CountriesResponse {
     pub data: Vec<Country>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/countries",
+    tag = "countries",
+    responses(
+        (status = 200, description = "Supported countries", body = CountriesResponse),
+        (status = 304, description = "Client copy is current"),
+    )
+)]
 pub async fn get_countries(
     State(state): State<AppState>,
-) -> Result<Json<CountriesResponse>, AppError> {
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
     tracing::info!("fetching countries");
 
+    let countries = state.cache.countries(&state.db_pool)?;
+    let response = CountriesResponse {
+        data: countries.items.iter().map(|c| c.into()).collect(),
+    };
+
+    cached_json(&headers, &response, countries.last_modified, LIST_CACHE_CONTROL)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CountryDetail {
+    #[serde(flatten)]
+    pub country: Country,
+    pub zone_count: i64,
+    pub coverage: Coverage,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CountryDetailResponse {
+    pub data: CountryDetail,
+}
+
+#[utoipa::path(
+    get,
+    path = "/countries/{code}",
+    tag = "countries",
+    params(("code" = String, Path, description = "Country code, e.g. `MY`")),
+    responses(
+        (status = 200, description = "Country with zone count and data coverage", body = CountryDetailResponse),
+        (status = 404, description = "Unknown country", body = ErrorResponse),
+    )
+)]
+pub async fn get_country(
+    Path(code): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<CountryDetailResponse>, AppError> {
+    tracing::info!("fetching country {}", code);
+
     let mut conn = state.db_pool.get()?;
 
-    let countries = select_countries(&mut conn)?;
-    let response = CountriesResponse {
-        data: countries.iter().map(|c| c.into()).collect(),
+    let country = select_country_by_code(&mut conn, &code)?;
+    let country = country.ok_or_else(|| AppError::NotFound(
+        format!("Country '{}' not found", code),
+    ))?;
+    let zone_count = count_zones_by_country(&mut conn, &country.code)?;
+    let coverage = select_date_range_for_country(&mut conn, &country.code)?;
+
+    let response = CountryDetailResponse {
+        data: CountryDetail {
+            country: (&country).into(),
+            zone_count,
+            coverage: coverage.into(),
+        },
     };
 
     Ok(Json(response))
@@ -0,0 +1,128 @@
+use axum::{
+    Json,
+    extract::State,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    models::prayer_times::{CoverageGapRow, ZoneCoverageRow, select_coverage, select_coverage_gaps},
+    routes::{
+        AppError, AppState, ErrorResponse,
+        extract::{Path, Query},
+    },
+};
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Gap {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ZoneCoverage {
+    pub zone: String,
+    pub first_date: Option<NaiveDate>,
+    pub last_date: Option<NaiveDate>,
+    pub total_days: i64,
+    pub missing_days: i64,
+    pub gaps: Vec<Gap>,
+}
+
+impl ZoneCoverage {
+    fn from_rows(row: &ZoneCoverageRow, gaps: &[CoverageGapRow]) -> Self {
+        let gaps: Vec<Gap> = gaps
+            .iter()
+            .filter(|g| g.zone_code == row.zone_code)
+            .map(|g| Gap {
+                from: g.gap_start,
+                to: g.gap_end,
+            })
+            .collect();
+        Self {
+            zone: row.zone_code.clone(),
+            first_date: row.first_date,
+            last_date: row.last_date,
+            total_days: row.total_days,
+            missing_days: gaps.iter().map(|g| (g.to - g.from).num_days() + 1).sum(),
+            gaps,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ZoneCoverageResponse {
+    pub data: ZoneCoverage,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CoverageResponse {
+    pub data: Vec<ZoneCoverage>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CoverageQuery {
+    /// Only zones of this country, e.g. `MY`
+    pub country: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/zones/{zone}/coverage",
+    tag = "coverage",
+    params(("zone" = String, Path, description = "Zone code, e.g. `SGR01`")),
+    responses(
+        (status = 200, description = "Stored date range and gaps for the zone", body = ZoneCoverageResponse),
+        (status = 404, description = "Unknown zone", body = ErrorResponse),
+    )
+)]
+pub async fn get_zone_coverage(
+    Path(zone): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<ZoneCoverageResponse>, AppError> {
+    tracing::info!("fetching coverage for zone {}", zone);
+
+    let mut conn = state.db_pool.get()?;
+
+    let rows = select_coverage(&mut conn, None, Some(&zone))?;
+    let row = rows.first().ok_or_else(|| AppError::NotFound(
+        format!("Zone '{}' not found", zone),
+    ))?;
+    let gaps = select_coverage_gaps(&mut conn, None, Some(&zone))?;
+
+    let response = ZoneCoverageResponse {
+        data: ZoneCoverage::from_rows(row, &gaps),
+    };
+
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/coverage",
+    tag = "coverage",
+    params(CoverageQuery),
+    responses(
+        (status = 200, description = "Stored date range and gaps for every zone", body = CoverageResponse),
+    )
+)]
+pub async fn get_coverage(
+    Query(params): Query<CoverageQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<CoverageResponse>, AppError> {
+    tracing::info!("fetching coverage");
+
+    let mut conn = state.db_pool.get()?;
+
+    let country = params.country.as_deref();
+    let rows = select_coverage(&mut conn, country, None)?;
+    let gaps = select_coverage_gaps(&mut conn, country, None)?;
+
+    let response = CoverageResponse {
+        data: rows.iter().map(|r| ZoneCoverage::from_rows(r, &gaps)).collect(),
+    };
+
+    Ok(Json(response))
+}
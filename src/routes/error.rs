@@ -0,0 +1,273 @@
+use axum::{
+    Json,
+    extract::Request,
+    http::{HeaderName, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::service::cache::CacheError;
+
+/// Seconds clients are asked to wait when no database connection is free.
+const RETRY_AFTER_SECS: u64 = 5;
+
+const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest client-supplied request id that is accepted as-is.
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Stable, machine-readable error codes. Clients should branch on these
+/// rather than on the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    NotFound,
+    BadRequest,
+    InvalidParameters,
+    Unauthorized,
+    Forbidden,
+    RateLimited,
+    QuotaExceeded,
+    ServiceUnavailable,
+    InternalError,
+}
+
+/// A problem with a single request parameter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// JSON body returned for every error response.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    /// Human-readable description
+    pub error: String,
+    pub code: ErrorCode,
+    /// Also sent as the `X-Request-Id` header; quote it when reporting issues
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+}
+
+#[derive(Debug)]
+pub enum AppError {
+    NotFound(String),
+    BadRequest(String),
+    /// One or more request parameters failed validation.
+    InvalidParameters(Vec<FieldError>),
+    /// Missing or unknown credentials where they are required.
+    Unauthorized(String),
+    /// Valid credentials that do not grant access to the resource.
+    Forbidden(String),
+    /// The client exceeded its request rate; retry after the given seconds.
+    RateLimited(u64),
+    /// The API key used up its daily quota; retry after the given seconds.
+    QuotaExceeded(u64),
+    /// No database connection became available in time.
+    Unavailable,
+    /// Logged with the request id; clients only see a generic message.
+    Internal(String),
+}
+
+impl AppError {
+    /// Validation failure for a single parameter.
+    pub fn invalid(field: &str, message: impl Into<String>) -> Self {
+        AppError::InvalidParameters(vec![FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        }])
+    }
+}
+
+/// Id of the request currently being handled, if inside [`request_id`].
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let request_id = current_request_id();
+        let mut retry_after = None;
+        let (status, code, error, details) = match self {
+            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, ErrorCode::NotFound, msg, vec![]),
+            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, ErrorCode::BadRequest, msg, vec![]),
+            AppError::InvalidParameters(details) => {
+                let error = match details.as_slice() {
+                    [single] => single.message.clone(),
+                    _ => "Invalid request parameters".to_string(),
+                };
+                (StatusCode::BAD_REQUEST, ErrorCode::InvalidParameters, error, details)
+            }
+            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized, msg, vec![]),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, ErrorCode::Forbidden, msg, vec![]),
+            AppError::RateLimited(secs) => {
+                retry_after = Some(secs);
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    ErrorCode::RateLimited,
+                    "Too many requests, please slow down".to_string(),
+                    vec![],
+                )
+            }
+            AppError::QuotaExceeded(secs) => {
+                retry_after = Some(secs);
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    ErrorCode::QuotaExceeded,
+                    "Daily quota for this API key is used up".to_string(),
+                    vec![],
+                )
+            }
+            AppError::Unavailable => {
+                retry_after = Some(RETRY_AFTER_SECS);
+                tracing::warn!(request_id = ?request_id, "database pool exhausted");
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    ErrorCode::ServiceUnavailable,
+                    "Service temporarily unavailable, please retry".to_string(),
+                    vec![],
+                )
+            }
+            AppError::Internal(msg) => {
+                tracing::error!(request_id = ?request_id, "internal error: {}", msg);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ErrorCode::InternalError,
+                    "Internal server error".to_string(),
+                    vec![],
+                )
+            }
+        };
+
+        let mut response = (
+            status,
+            Json(ErrorResponse {
+                error,
+                code,
+                request_id,
+                details,
+            }),
+        )
+            .into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
+    }
+}
+
+impl From<diesel::r2d2::PoolError> for AppError {
+    fn from(_: diesel::r2d2::PoolError) -> Self {
+        AppError::Unavailable
+    }
+}
+
+impl From<diesel::result::Error> for AppError {
+    fn from(err: diesel::result::Error) -> Self {
+        AppError::Internal(format!("database error: {}", err))
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::Internal(format!("serialization error: {}", err))
+    }
+}
+
+impl From<CacheError> for AppError {
+    fn from(err: CacheError) -> Self {
+        match err {
+            CacheError::Pool(e) => e.into(),
+            CacheError::Db(e) => e.into(),
+        }
+    }
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+/// Middleware assigning every request an id: the caller's `X-Request-Id` when
+/// it looks sane, a fresh UUID otherwise. The id is echoed in the response
+/// header and included in error bodies and logs.
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&X_REQUEST_ID)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| is_valid_request_id(v))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(X_REQUEST_ID, value);
+    }
+    response
+}
+
+/// Fallback for paths that match no route.
+pub async fn route_not_found() -> AppError {
+    AppError::NotFound("No such endpoint".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_internal_errors_hide_details() {
+        let err: AppError = diesel::result::Error::NotFound.into();
+        let response = REQUEST_ID
+            .scope("abc".to_string(), async { err.into_response() })
+            .await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "internal_error");
+        assert_eq!(body["error"], "Internal server error");
+        assert_eq!(body["request_id"], "abc");
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_sets_retry_after() {
+        let response = AppError::Unavailable.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "5");
+        assert_eq!(body_json(response).await["code"], "service_unavailable");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_parameters_lists_fields() {
+        let response = AppError::invalid("to", "'to' is too far").into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = body_json(response).await;
+        assert_eq!(body["code"], "invalid_parameters");
+        assert_eq!(body["error"], "'to' is too far");
+        assert_eq!(body["details"][0]["field"], "to");
+    }
+
+    #[test]
+    fn test_is_valid_request_id() {
+        assert!(is_valid_request_id("3f2a-b_c.1"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("a b"));
+        assert!(!is_valid_request_id(&"a".repeat(65)));
+    }
+}
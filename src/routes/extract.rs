@@ -0,0 +1,209 @@
+use std::str::FromStr;
+
+use axum::{
+    extract::{
+        FromRequestParts, RawPathParams,
+        path::ErrorKind,
+        rejection::{PathRejection, RawPathParamsRejection},
+    },
+    http::request::Parts,
+};
+use chrono::{Months, NaiveDate, TimeDelta};
+use serde::{Deserialize, Deserializer, de::DeserializeOwned};
+
+use crate::routes::AppError;
+
+/// Query string extractor that rejects with the JSON [`AppError`] shape,
+/// naming the offending parameter.
+pub struct Query<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or_default();
+        let deserializer = serde_urlencoded::Deserializer::new(form_urlencoded::parse(query.as_bytes()));
+        serde_path_to_error::deserialize(deserializer)
+            .map(Query)
+            .map_err(|e| {
+                let message = e.inner().to_string();
+                match e.path().to_string() {
+                    // Missing fields are reported at the root
+                    path if path == "." => {
+                        let field = message.split('`').nth(1).unwrap_or_default();
+                        AppError::invalid(field, format!("'{}' is required", field))
+                    }
+                    field => AppError::invalid(&field, format!("invalid '{}': {}", field, message)),
+                }
+            })
+    }
+}
+
+/// Path extractor that rejects with the JSON [`AppError`] shape, naming the
+/// offending segment.
+pub struct Path<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let rejection = match axum::extract::Path::<T>::from_request_parts(parts, state).await {
+            Ok(axum::extract::Path(value)) => return Ok(Path(value)),
+            Err(rejection) => rejection,
+        };
+
+        let PathRejection::FailedToDeserializePathParams(err) = rejection else {
+            return Err(AppError::Internal(rejection.body_text()));
+        };
+        let (key, value) = match err.kind() {
+            ErrorKind::ParseErrorAtKey { key, value, .. }
+            | ErrorKind::DeserializeError { key, value, .. } => (key.clone(), value.clone()),
+            ErrorKind::ParseErrorAtIndex { index, value, .. } => {
+                // Tuples only know the position; look the name up in the route
+                let params: Result<RawPathParams, RawPathParamsRejection> =
+                    RawPathParams::from_request_parts(parts, state).await;
+                let key = params
+                    .ok()
+                    .and_then(|p| p.iter().nth(*index).map(|(k, _)| k.to_string()))
+                    .unwrap_or_default();
+                (key, value.clone())
+            }
+            _ => return Err(AppError::BadRequest(err.body_text())),
+        };
+        Err(AppError::invalid(&key, format!("invalid value '{}' for '{}'", value, key)))
+    }
+}
+
+/// A date parameter: `YYYY-MM-DD`, a whole month `YYYY-MM`, `today`,
+/// `tomorrow`, `yesterday` or an offset from today such as `+30d` / `-7d`.
+/// The `+` is optional since an unencoded one arrives as a space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateSpec {
+    Date(NaiveDate),
+    Month(NaiveDate),
+    DaysFromToday(i64),
+}
+
+impl DateSpec {
+    /// Resolves against `today` (in the zone's timezone). Months resolve to
+    /// their first day, or their last day when `end` is set.
+    pub fn resolve(&self, today: NaiveDate, end: bool) -> Option<NaiveDate> {
+        match *self {
+            DateSpec::Date(date) => Some(date),
+            DateSpec::Month(start) if end => start.checked_add_months(Months::new(1))?.pred_opt(),
+            DateSpec::Month(start) => Some(start),
+            DateSpec::DaysFromToday(days) => today.checked_add_signed(TimeDelta::try_days(days)?),
+        }
+    }
+}
+
+impl FromStr for DateSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || {
+            format!(
+                "'{}' is not a date; use YYYY-MM-DD, YYYY-MM, today, tomorrow, yesterday or ±Nd",
+                s
+            )
+        };
+
+        match s.to_ascii_lowercase().as_str() {
+            "today" => return Ok(DateSpec::DaysFromToday(0)),
+            "tomorrow" => return Ok(DateSpec::DaysFromToday(1)),
+            "yesterday" => return Ok(DateSpec::DaysFromToday(-1)),
+            _ => {}
+        }
+
+        if let Some(days) = s.strip_suffix('d') {
+            return days.parse().map(DateSpec::DaysFromToday).map_err(|_| invalid());
+        }
+        if s.len() == 7 {
+            return NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d")
+                .map(DateSpec::Month)
+                .map_err(|_| invalid());
+        }
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map(DateSpec::Date)
+            .map_err(|_| invalid())
+    }
+}
+
+impl<'de> Deserialize<'de> for DateSpec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_date_spec() {
+        assert_eq!("2026-03-05".parse(), Ok(DateSpec::Date(date(2026, 3, 5))));
+        assert_eq!("2026-03".parse(), Ok(DateSpec::Month(date(2026, 3, 1))));
+        assert_eq!("Today".parse(), Ok(DateSpec::DaysFromToday(0)));
+        assert_eq!("tomorrow".parse(), Ok(DateSpec::DaysFromToday(1)));
+        assert_eq!("+30d".parse(), Ok(DateSpec::DaysFromToday(30)));
+        assert_eq!("-7d".parse(), Ok(DateSpec::DaysFromToday(-7)));
+        assert_eq!("30d".parse(), Ok(DateSpec::DaysFromToday(30)));
+        assert!("+d".parse::<DateSpec>().is_err());
+        assert!("2026-13".parse::<DateSpec>().is_err());
+        assert!("2026-02-30".parse::<DateSpec>().is_err());
+        assert!("soon".parse::<DateSpec>().is_err());
+    }
+
+    #[test]
+    fn test_resolve_date_spec() {
+        let today = date(2028, 2, 10);
+        assert_eq!(DateSpec::Month(date(2028, 2, 1)).resolve(today, false), Some(date(2028, 2, 1)));
+        assert_eq!(DateSpec::Month(date(2028, 2, 1)).resolve(today, true), Some(date(2028, 2, 29)));
+        assert_eq!(DateSpec::DaysFromToday(30).resolve(today, false), Some(date(2028, 3, 11)));
+        assert_eq!(DateSpec::DaysFromToday(i64::MAX).resolve(today, false), None);
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Params {
+        #[allow(dead_code)]
+        from: DateSpec,
+        #[allow(dead_code)]
+        lat: Option<f64>,
+    }
+
+    async fn reject(uri: &str) -> serde_json::Value {
+        use axum::response::IntoResponse;
+
+        let (mut parts, _) = axum::http::Request::get(uri).body(()).unwrap().into_parts();
+        let Err(err) = Query::<Params>::from_request_parts(&mut parts, &()).await else {
+            panic!("{} should be rejected", uri);
+        };
+        let body = axum::body::to_bytes(err.into_response().into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_query_rejection_names_field() {
+        let body = reject("/x?from=soon").await;
+        assert_eq!(body["code"], "invalid_parameters");
+        assert_eq!(body["details"][0]["field"], "from");
+
+        assert_eq!(reject("/x?from=today&lat=north").await["details"][0]["field"], "lat");
+        assert_eq!(reject("/x").await["details"][0]["field"], "from");
+    }
+}
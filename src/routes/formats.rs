@@ -0,0 +1,138 @@
+use axum::{
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+/// Output format selected with the `format` query parameter.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Json,
+    Csv,
+    Ics,
+}
+
+/// A single point-in-time calendar entry.
+pub struct CalendarEvent {
+    pub uid: String,
+    pub summary: String,
+    pub start: i64, // Unix timestamp
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Renders a CSV document with a header row.
+pub fn render_csv(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut out = header.join(",");
+    out.push_str("\r\n");
+    for row in rows {
+        let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn ics_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn ics_timestamp(ts: i64) -> String {
+    DateTime::<Utc>::from_timestamp(ts, 0)
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// Renders an iCalendar (RFC 5545) document with zero-length events.
+pub fn render_ics(name: &str, events: &[CalendarEvent]) -> String {
+    let stamp = ics_timestamp(Utc::now().timestamp());
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//simplesolat//simplesolat-api//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", ics_text(name)),
+    ];
+    for event in events {
+        let start = ics_timestamp(event.start);
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", event.uid),
+            format!("DTSTAMP:{}", stamp),
+            format!("DTSTART:{}", start),
+            format!("DTEND:{}", start),
+            format!("SUMMARY:{}", ics_text(&event.summary)),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = lines.join("\r\n");
+    out.push_str("\r\n");
+    out
+}
+
+/// Wraps a rendered CSV document in a downloadable response.
+pub fn csv_response(filename: &str, body: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.csv\"", filename)),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Wraps a rendered iCalendar document in a downloadable response.
+pub fn ics_response(filename: &str, body: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.ics\"", filename)),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_csv_escapes_fields() {
+        let csv = render_csv(&["a", "b"], &[vec!["1".to_string(), "x, \"y\"".to_string()]]);
+        assert_eq!(csv, "a,b\r\n1,\"x, \"\"y\"\"\"\r\n");
+    }
+
+    #[test]
+    fn test_render_ics_events() {
+        let ics = render_ics(
+            "Ramadan",
+            &[CalendarEvent {
+                uid: "SGR01-2026-02-19-imsak@simplesolat".to_string(),
+                summary: "Imsak".to_string(),
+                start: 1771451400,
+            }],
+        );
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("DTSTART:20260218T215000Z\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    }
+}
@@ -2,11 +2,23 @@ use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
 use diesel::RunQueryDsl;
 
 use super::AppState;
+use crate::models::db::select_sync_locks;
 
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses(
+        (status = 200, description = "Service and database are up, with cache statistics and the sync locks currently held"),
+        (status = 503, description = "Database is unreachable"),
+    )
+)]
 pub async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
-    let db_ok = state.db_pool.get().ok().and_then(|mut conn| {
-        diesel::sql_query("SELECT 1").execute(&mut conn).ok()
+    let mut conn = state.db_pool.get().ok();
+    let db_ok = conn.as_mut().and_then(|conn| {
+        diesel::sql_query("SELECT 1").execute(conn).ok()
     }).is_some();
+    let sync_locks = conn.as_mut().and_then(|conn| select_sync_locks(conn).ok());
 
     let status = if db_ok {
         StatusCode::OK
@@ -20,6 +32,13 @@ pub async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
             "status": if db_ok { "ok" } else { "unavailable" },
             "service": "simplesolat-api",
             "db": if db_ok { "connected" } else { "disconnected" },
+            "cache": {
+                "zones": state.cache.zones_stats.snapshot(),
+                "countries": state.cache.countries_stats.snapshot(),
+                "prayer_times": state.cache.prayer_times_stats.snapshot(),
+                "prayer_time_blocks": state.cache.prayer_time_blocks(),
+            },
+            "sync_locks": sync_locks,
         })),
     )
 }
@@ -1,71 +1,175 @@
+pub mod access;
+pub mod admin;
+pub mod caching;
 pub mod countries;
+pub mod coverage;
+pub mod cors;
+pub mod error;
+pub mod extract;
+pub mod formats;
 pub mod health;
+pub mod openapi;
 pub mod prayer_times;
+pub mod qibla;
+pub mod ramadan;
+pub mod rate_limit;
+pub mod sync;
+pub mod versioning;
 pub mod zones;
 
-use axum::{Json, Router, http::StatusCode, response::IntoResponse, routing::get};
-use tower_http::cors::CorsLayer;
+use std::sync::Arc;
+
+use axum::{
+    Router, middleware,
+    routing::{MethodRouter, get, post},
+};
+use tower_http::compression::CompressionLayer;
+
+pub use error::{AppError, ErrorResponse};
 
 use crate::{
-    models::db::{DbPool, connect_db},
+    config::Config,
+    models::db::DbPool,
     routes::{
-        countries::get_countries,
+        access::require_admin,
+        admin::{get_job, post_sync_all, post_sync_country, post_sync_zone},
+        countries::{get_countries, get_country},
+        coverage::{get_coverage, get_zone_coverage},
+        error::{request_id, route_not_found},
         health::health_check,
+        openapi::{get_docs, get_openapi},
         prayer_times::get_prayer_times,
-        zones::get_zones,
+        qibla::get_qibla,
+        ramadan::get_ramadan_times,
+        rate_limit::rate_limit,
+        sync::get_sync_schedule,
+        versioning::{UNVERSIONED, deprecation_headers},
+        zones::{get_zone, get_zones, search_zones},
+    },
+    service::{
+        api_keys::{AccessControl, spawn_usage_flusher},
+        cache::{ReadCache, listen_for_changes},
+        jobs::SyncJobs,
+        rate_limit::IpRateLimiter,
+        schedule::SyncSchedules,
+        search::ZoneSearch,
     },
 };
 
 #[derive(Clone)]
 pub struct AppState {
     pub db_pool: DbPool,
+    pub zone_search: Arc<ZoneSearch>,
+    pub cache: Arc<ReadCache>,
+    pub access: Arc<AccessControl>,
+    pub rate_limiter: Arc<IpRateLimiter>,
+    pub sync_schedules: Arc<SyncSchedules>,
+    pub jobs: Arc<SyncJobs>,
 }
 
-pub async fn create_app_router() -> Router {
-    tracing::info!("connecting to database");
-    let db_pool = connect_db();
-
+/// Builds the application. Syncs queued through the admin endpoints run on
+/// the worker of `jobs`.
+pub async fn create_app_router(config: &Config, db_pool: DbPool, jobs: Arc<SyncJobs>) -> Router {
     // Initialize app state
-    let state = AppState { db_pool };
+    let state = AppState {
+        db_pool: db_pool.clone(),
+        zone_search: Arc::new(ZoneSearch::default()),
+        cache: Arc::new(ReadCache::default()),
+        access: Arc::new(AccessControl::default()),
+        rate_limiter: Arc::new(IpRateLimiter::new(config.rate_limit.clone())),
+        sync_schedules: Arc::new(config.sync.schedules.clone()),
+        jobs,
+    };
+
+    // Drop cached data whenever a sync process reports changes
+    let (cache, zone_search) = (state.cache.clone(), state.zone_search.clone());
+    listen_for_changes(config.database.url.clone(), move |_| {
+        cache.invalidate();
+        zone_search.invalidate();
+    });
+
+    spawn_usage_flusher(state.access.clone(), state.db_pool.clone());
 
     // Build the router
+    let api = Router::new()
+        .nest("/v1", routes(api_routes()))
+        .nest("/v2", routes(api_routes()))
+        .merge(routes(api_routes()).route_layer(middleware::from_fn_with_state(UNVERSIONED, deprecation_headers)))
+        // Per-key limits, or per-IP without a valid key
+        .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit));
+
+    let public = routes(root_routes())
+        .merge(api)
+        .layer(config.cors.public.layer());
+
     Router::new()
-        .route("/health", get(health_check))
-        .route("/countries", get(get_countries))
-        .route("/prayer-times/by-zone/{zone}", get(get_prayer_times))
-        .route("/zones", get(get_zones))
-        .layer(CorsLayer::permissive())
+        .merge(public)
+        .merge(
+            routes(admin_routes())
+                .route_layer(middleware::from_fn_with_state(state.clone(), require_admin))
+                .layer(config.cors.admin.layer()),
+        )
+        .fallback(route_not_found)
+        .layer(middleware::from_fn(request_id))
+        // gzip/brotli/zstd, negotiated from Accept-Encoding
+        .layer(CompressionLayer::new())
         .with_state(state)
 }
 
-// Error handling
-#[derive(Debug)]
-pub enum AppError {
-    NotFound(String),
-    BadRequest(String),
-    Internal(String),
+/// Paths and their handlers, kept as lists so tests can see every path.
+type Routes = Vec<(&'static str, MethodRouter<AppState>)>;
+
+fn routes(routes: Routes) -> Router<AppState> {
+    routes
+        .into_iter()
+        .fold(Router::new(), |router, (path, handler)| router.route(path, handler))
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> axum::response::Response {
-        let (status, message) = match self {
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-            AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
-        };
-        (
-            status,
-            Json(serde_json::json!({ "error": message })),
-        )
-            .into_response()
-    }
+/// Versioned API routes. v2 starts out identical to v1; breaking changes to
+/// response shapes go into v2 only, and v1 gets a [`versioning::Deprecation`]
+/// schedule once clients should move over.
+fn api_routes() -> Routes {
+    vec![
+        ("/countries", get(get_countries)),
+        ("/countries/{code}", get(get_country)),
+        ("/coverage", get(get_coverage)),
+        ("/prayer-times/by-zone/{zone}", get(get_prayer_times)),
+        ("/prayer-times/by-zone/{zone}/ramadan/{hijri_year}", get(get_ramadan_times)),
+        ("/qibla", get(get_qibla)),
+        ("/zones", get(get_zones)),
+        ("/zones/search", get(search_zones)),
+        ("/zones/{zone}", get(get_zone)),
+        ("/zones/{zone}/coverage", get(get_zone_coverage)),
+    ]
+}
+
+/// Operational routes served at the root only, outside the limits.
+fn root_routes() -> Routes {
+    vec![
+        ("/docs", get(get_docs)),
+        ("/health", get(health_check)),
+        ("/openapi.json", get(get_openapi)),
+        ("/sync/schedule", get(get_sync_schedule)),
+    ]
+}
+
+/// Operator endpoints under `/admin`, kept apart from [`api_routes`] so they
+/// get their own CORS policy and are never versioned or rate limited per IP.
+fn admin_routes() -> Routes {
+    vec![
+        ("/admin/jobs/{id}", get(get_job)),
+        ("/admin/sync", post(post_sync_all)),
+        ("/admin/sync/{country}", post(post_sync_country)),
+        ("/admin/sync/{country}/{zone}", post(post_sync_zone)),
+    ]
 }
 
-impl<E> From<E> for AppError
-where
-    E: std::error::Error,
-{
-    fn from(err: E) -> Self {
-        AppError::Internal(err.to_string())
-    }
+/// Every routed path, without version prefixes.
+#[cfg(test)]
+pub(crate) fn route_paths() -> Vec<&'static str> {
+    [api_routes(), root_routes(), admin_routes()]
+        .into_iter()
+        .flatten()
+        .map(|(path, _)| path)
+        .collect()
 }
@@ -0,0 +1,127 @@
+use axum::{Json, response::Html};
+use utoipa::{
+    Modify, OpenApi,
+    openapi::{
+        self, Server,
+        security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme},
+    },
+};
+
+use crate::routes::{admin, countries, coverage, health, prayer_times, qibla, ramadan, sync, zones};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Simplesolat API",
+        description = "Prayer times for Malaysia, Singapore, Indonesia, Brunei and Sri Lanka."
+    ),
+    paths(
+        admin::get_job,
+        admin::post_sync_all,
+        admin::post_sync_country,
+        admin::post_sync_zone,
+        countries::get_countries,
+        countries::get_country,
+        coverage::get_coverage,
+        coverage::get_zone_coverage,
+        health::health_check,
+        prayer_times::get_prayer_times,
+        qibla::get_qibla,
+        ramadan::get_ramadan_times,
+        sync::get_sync_schedule,
+        zones::get_zone,
+        zones::get_zones,
+        zones::search_zones,
+    ),
+    modifiers(&VersionServers, &ApiKeySecurity)
+)]
+pub struct ApiDoc;
+
+/// Paths served outside the versioned routers, besides the admin ones.
+const UNVERSIONED_PATHS: [&str; 2] = ["/health", "/sync/schedule"];
+
+const ADMIN_PREFIX: &str = "/admin/";
+
+/// Documents the API paths under each version prefix, and the operational
+/// ones at the root.
+struct VersionServers;
+
+impl Modify for VersionServers {
+    fn modify(&self, openapi: &mut openapi::OpenApi) {
+        openapi.servers = Some(vec![
+            Server::new("/v1"),
+            Server::new("/v2"),
+        ]);
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            if UNVERSIONED_PATHS.contains(&path.as_str()) || path.starts_with(ADMIN_PREFIX) {
+                item.servers = Some(vec![Server::new("/")]);
+            }
+        }
+    }
+}
+
+/// Keys are optional: requests without one get the anonymous per-IP limit.
+struct ApiKeySecurity;
+
+impl Modify for ApiKeySecurity {
+    fn modify(&self, openapi: &mut openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        openapi.security = Some(vec![
+            SecurityRequirement::default(),
+            SecurityRequirement::new("api_key", Vec::<String>::new()),
+            SecurityRequirement::new("bearer", Vec::<String>::new()),
+        ]);
+    }
+}
+
+/// Redoc page rendering `/openapi.json`.
+const DOCS_HTML: &str = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>Simplesolat API</title>
+    <meta charset="utf-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1" />
+  </head>
+  <body>
+    <redoc spec-url="/openapi.json"></redoc>
+    <script src="https://cdn.redoc.ly/redoc/latest/bundles/redoc.standalone.js"></script>
+  </body>
+</html>
+"#;
+
+pub async fn get_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+pub async fn get_docs() -> Html<&'static str> {
+    Html(DOCS_HTML)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Routes that serve the documentation itself.
+    const UNDOCUMENTED: [&str; 2] = ["/docs", "/openapi.json"];
+
+    #[test]
+    fn test_every_route_is_documented() {
+        let spec = ApiDoc::openapi();
+        let routes = crate::routes::route_paths();
+
+        for route in routes.iter().filter(|r| !UNDOCUMENTED.contains(r)) {
+            assert!(spec.paths.paths.contains_key(*route), "route {} missing from OpenAPI spec", route);
+        }
+        for path in spec.paths.paths.keys() {
+            assert!(routes.contains(&path.as_str()), "OpenAPI path {} is not routed", path);
+        }
+    }
+}
@@ -1,16 +1,25 @@
+use std::{io, ops::RangeInclusive, str::FromStr};
+
 use axum::{
     Json,
-    extract::{Path, Query, State},
+    body::Body,
+    extract::State,
+    http::{HeaderMap, header},
+    response::Response,
 };
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    models::{
-        prayer_times::{SelectPrayerTime, select_prayer_times_for_zone},
-        zones::select_zone_by_code,
+    models::prayer_times::{SelectPrayerTime, select_prayer_times_page, select_prayer_times_version},
+    routes::{
+        AppError, AppState, ErrorResponse,
+        extract::{DateSpec, Path, Query},
+        caching::{PRAYER_TIMES_CACHE_CONTROL, Validators, etag_for, until_local_midnight},
     },
-    routes::{AppError, AppState},
 };
 
 fn datetime_to_timestamp(date: NaiveDate, time: NaiveTime, tz: chrono_tz::Tz) -> i64 {
@@ -19,8 +28,96 @@ fn datetime_to_timestamp(date: NaiveDate, time: NaiveTime, tz: chrono_tz::Tz) ->
     dt.timestamp()
 }
 
+/// Ranges longer than this (in days) are read page by page and streamed
+/// instead of being assembled from cached month blocks.
+const STREAM_MIN_DAYS: i64 = 93;
+
+/// Rows per database page and body chunk, and chunks buffered ahead of the
+/// client, when streaming.
+const STREAM_CHUNK_ROWS: usize = 64;
+const STREAM_BUFFERED_CHUNKS: usize = 4;
+
+/// Largest offset (in minutes, either direction) accepted for a single prayer.
+const MAX_ADJUST_MINUTES: i64 = 30;
+
+/// Per-prayer minute offsets (ihtiyati) added on top of the official times.
+/// Parsed from `adjust=fajr:+2,maghrib:+3`; unlisted prayers stay at 0.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Adjustments {
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub imsak: i64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub fajr: i64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub syuruk: i64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub dhuhr: i64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub asr: i64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub maghrib: i64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub isha: i64,
+}
+
+fn is_zero(v: &i64) -> bool {
+    *v == 0
+}
+
+impl Adjustments {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl FromStr for Adjustments {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut adjust = Self::default();
+        let mut seen = Vec::new();
+
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, minutes) = part
+                .split_once(':')
+                .ok_or_else(|| format!("invalid adjustment '{}', expected <prayer>:<minutes>", part))?;
+            let name = name.trim().to_ascii_lowercase();
+            let minutes = minutes.trim();
+            let minutes: i64 = minutes
+                .strip_prefix('+')
+                .unwrap_or(minutes)
+                .parse()
+                .map_err(|_| format!("invalid minutes '{}' for {}", minutes, name))?;
+            if minutes.abs() > MAX_ADJUST_MINUTES {
+                return Err(format!(
+                    "adjustment for {} must be within ±{} minutes",
+                    name, MAX_ADJUST_MINUTES
+                ));
+            }
+            if seen.contains(&name) {
+                return Err(format!("duplicate adjustment for {}", name));
+            }
+
+            let slot = match name.as_str() {
+                "imsak" => &mut adjust.imsak,
+                "fajr" => &mut adjust.fajr,
+                "syuruk" => &mut adjust.syuruk,
+                "dhuhr" => &mut adjust.dhuhr,
+                "asr" => &mut adjust.asr,
+                "maghrib" => &mut adjust.maghrib,
+                "isha" => &mut adjust.isha,
+                _ => return Err(format!("unknown prayer '{}'", name)),
+            };
+            *slot = minutes;
+            seen.push(name);
+        }
+
+        Ok(adjust)
+    }
+}
+
 // Types matching your mobile app's expected format
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct WaktuSolat {
     pub date: NaiveDate,
     pub zone: String,
@@ -34,72 +131,375 @@ pub struct WaktuSolat {
 }
 
 impl WaktuSolat {
-    fn from_prayer_time(value: &SelectPrayerTime, tz: chrono_tz::Tz) -> Self {
+    pub fn from_prayer_time(value: &SelectPrayerTime, tz: chrono_tz::Tz, adjust: &Adjustments) -> Self {
+        let ts = |time: NaiveTime, minutes: i64| datetime_to_timestamp(value.date, time, tz) + minutes * 60;
         Self {
             date: value.date,
             zone: value.zone_code.to_string(),
-            imsak: datetime_to_timestamp(value.date, value.imsak, tz),
-            fajr: datetime_to_timestamp(value.date, value.fajr, tz),
-            syuruk: datetime_to_timestamp(value.date, value.syuruk, tz),
-            dhuhr: datetime_to_timestamp(value.date, value.dhuhr, tz),
-            asr: datetime_to_timestamp(value.date, value.asr, tz),
-            maghrib: datetime_to_timestamp(value.date, value.maghrib, tz),
-            isha: datetime_to_timestamp(value.date, value.isha, tz),
+            imsak: ts(value.imsak, adjust.imsak),
+            fajr: ts(value.fajr, adjust.fajr),
+            syuruk: ts(value.syuruk, adjust.syuruk),
+            dhuhr: ts(value.dhuhr, adjust.dhuhr),
+            asr: ts(value.asr, adjust.asr),
+            maghrib: ts(value.maghrib, adjust.maghrib),
+            isha: ts(value.isha, adjust.isha),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct WaktuSolatResponse {
     pub data: Vec<WaktuSolat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<WaktuSolatMeta>,
+}
+
+/// Describes how the returned times differ from the official ones.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WaktuSolatMeta {
+    pub adjust: Adjustments,
+}
+
+/// Writes a [`WaktuSolatResponse`] document one row at a time, producing the
+/// same JSON as serializing the whole response at once.
+pub struct WaktuSolatEncoder {
+    tz: chrono_tz::Tz,
+    adjust: Adjustments,
+    rows: usize,
+}
+
+impl WaktuSolatEncoder {
+    pub fn new(tz: chrono_tz::Tz, adjust: Adjustments) -> Self {
+        Self { tz, adjust, rows: 0 }
+    }
+
+    /// Number of rows written so far.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Appends a row to `out`, opening the document first if needed.
+    pub fn push(&mut self, pt: &SelectPrayerTime, out: &mut Vec<u8>) -> serde_json::Result<()> {
+        out.extend_from_slice(if self.rows == 0 { b"{\"data\":[" } else { b"," });
+        serde_json::to_writer(&mut *out, &WaktuSolat::from_prayer_time(pt, self.tz, &self.adjust))?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Closes the document, appending `meta` when adjustments were applied.
+    pub fn finish(self, out: &mut Vec<u8>) -> serde_json::Result<()> {
+        if self.rows == 0 {
+            out.extend_from_slice(b"{\"data\":[");
+        }
+        out.push(b']');
+        if !self.adjust.is_empty() {
+            out.extend_from_slice(b",\"meta\":");
+            serde_json::to_writer(&mut *out, &WaktuSolatMeta { adjust: self.adjust })?;
+        }
+        out.push(b'}');
+        Ok(())
+    }
 }
 
 // Query parameters for the prayer times endpoint
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct PrayerQuery {
-    pub from: NaiveDate,
-    pub to: NaiveDate,
+    /// Start date: `YYYY-MM-DD`, `YYYY-MM`, `today`, `tomorrow` or `±Nd`.
+    /// Defaults to the first day of the current month.
+    #[param(value_type = Option<String>, example = "2026-01")]
+    pub from: Option<DateSpec>,
+    /// End date, in the same formats, at most 750 days after `from`.
+    /// Defaults to the last day of the month of `from`.
+    #[param(value_type = Option<String>, example = "+30d")]
+    pub to: Option<DateSpec>,
+    /// Minute offsets per prayer, e.g. `fajr:+2,maghrib:+3` (max ±30)
+    pub adjust: Option<String>,
+}
+
+/// Resolves a requested range against the zone's local `today`. Without
+/// `from` the range starts on the first of the current month; without `to`
+/// it ends with the month of `from`.
+pub fn resolve_range(
+    from: Option<DateSpec>,
+    to: Option<DateSpec>,
+    today: NaiveDate,
+) -> Result<RangeInclusive<NaiveDate>, AppError> {
+    let from = match from {
+        Some(spec) => spec.resolve(today, false),
+        None => today.with_day(1),
+    }
+    .ok_or_else(|| AppError::invalid("from", "'from' is out of range"))?;
+    let to = match to {
+        Some(spec) => spec.resolve(today, true),
+        None => DateSpec::Month(from.with_day(1).expect("invalid month start")).resolve(today, true),
+    }
+    .ok_or_else(|| AppError::invalid("to", "'to' is out of range"))?;
+    Ok(from..=to)
 }
 
+#[utoipa::path(
+    get,
+    path = "/prayer-times/by-zone/{zone}",
+    tag = "prayer-times",
+    params(("zone" = String, Path, description = "Zone code, e.g. `SGR01`"), PrayerQuery),
+    responses(
+        (status = 200, description = "Prayer times as Unix timestamps", body = WaktuSolatResponse),
+        (status = 304, description = "Client copy is current"),
+        (status = 400, description = "Invalid date range or adjustment", body = ErrorResponse),
+        (status = 404, description = "Unknown zone", body = ErrorResponse),
+    )
+)]
 pub async fn get_prayer_times(
     Path(zone): Path<String>,
     Query(params): Query<PrayerQuery>,
     State(state): State<AppState>,
-) -> Result<Json<WaktuSolatResponse>, AppError> {
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    // Look up zone to determine timezone
+    let zone_info = state.cache.zone(&state.db_pool, &zone)?;
+    let zone_info = zone_info.ok_or_else(|| AppError::NotFound(
+        format!("Zone '{}' not found", zone),
+    ))?;
+    let tz = zone_info.timezone();
+
+    // Relative dates are relative to the zone's local date
+    let now = Utc::now();
+    let today = now.with_timezone(&tz).date_naive();
+    // A default `to` is the end of the month of `from`, so it only moves
+    // with a relative `from`
+    let relative = matches!(params.from, None | Some(DateSpec::DaysFromToday(_)))
+        || matches!(params.to, Some(DateSpec::DaysFromToday(_)));
+    let cache_control = if relative {
+        until_local_midnight(tz, now)
+    } else {
+        PRAYER_TIMES_CACHE_CONTROL.to_string()
+    };
+    let (from, to) = resolve_range(params.from, params.to, today)?.into_inner();
+
     // Validate date range
-    if params.from > params.to {
-        return Err(AppError::BadRequest(
-            "'from' date must be before or equal to 'to' date".to_string(),
+    if from > to {
+        return Err(AppError::invalid(
+            "from",
+            "'from' date must be before or equal to 'to' date",
         ));
     }
     let max_days = 750; // >2 years
-    if (params.to - params.from).num_days() > max_days {
-        return Err(AppError::BadRequest(
+    if (to - from).num_days() > max_days {
+        return Err(AppError::invalid(
+            "to",
             format!("Date range cannot exceed {} days", max_days),
         ));
     }
 
+    let adjust = match params.adjust {
+        Some(ref s) => s.parse::<Adjustments>().map_err(|e| AppError::invalid("adjust", e))?,
+        None => Adjustments::default(),
+    };
+
     tracing::info!(
         "fetching prayer times for zone {}, from {} to {}",
         zone,
-        params.from,
-        params.to
+        from,
+        to
     );
 
-    // Get a connection from the pool
-    let mut conn = state.db_pool.get()?;
+    if (to - from).num_days() + 1 > STREAM_MIN_DAYS {
+        return stream_prayer_times(state, headers, zone, tz, from..=to, adjust, &cache_control);
+    }
 
-    // Look up zone to determine timezone
-    let zone_info = select_zone_by_code(&mut conn, &zone)?;
-    let zone_info = zone_info.ok_or_else(|| AppError::NotFound(
-        format!("Zone '{}' not found", zone),
-    ))?;
-    let tz = zone_info.timezone();
+    let pts = state.cache.prayer_times(&state.db_pool, &zone, from, to)?;
+
+    let last_modified = pts.iter().map(|pt| pt.updated_at).max();
+    let validators =
+        prayer_times_validators(&zone, tz, from, to, &adjust, pts.len() as i64, last_modified)?;
+    if validators.is_not_modified(&headers) {
+        return Ok(validators.not_modified(&cache_control));
+    }
 
-    let pts = select_prayer_times_for_zone(&mut conn, &zone, params.from, params.to)?;
     let response = WaktuSolatResponse {
-        data: pts.iter().map(|pt| WaktuSolat::from_prayer_time(pt, tz)).collect(),
+        data: pts
+            .iter()
+            .map(|pt| WaktuSolat::from_prayer_time(pt, tz, &adjust))
+            .collect(),
+        meta: (!adjust.is_empty()).then_some(WaktuSolatMeta { adjust }),
     };
 
-    Ok(Json(response))
+    Ok(validators.respond(Json(response), &cache_control))
+}
+
+// Rows are inserted, or overwritten with a new `updated_at`, so the response
+// is fully determined by the request and the row count / newest `updated_at`
+// in range.
+fn prayer_times_validators(
+    zone: &str,
+    tz: chrono_tz::Tz,
+    from: NaiveDate,
+    to: NaiveDate,
+    adjust: &Adjustments,
+    count: i64,
+    last_modified: Option<NaiveDateTime>,
+) -> Result<Validators, AppError> {
+    let adjust_key = serde_json::to_string(adjust)?;
+    let version = format!("{}:{:?}", count, last_modified);
+    Ok(Validators {
+        etag: etag_for(&[
+            zone.as_bytes(),
+            tz.name().as_bytes(),
+            from.to_string().as_bytes(),
+            to.to_string().as_bytes(),
+            adjust_key.as_bytes(),
+            version.as_bytes(),
+        ]),
+        last_modified,
+    })
+}
+
+/// Serves a long range without holding it in memory, bypassing the month
+/// block cache. Rows are read in pages of [`STREAM_CHUNK_ROWS`] on a blocking
+/// thread, each on a connection that goes back to the pool before the page
+/// is sent, so a slow client never holds one. Each page is encoded and
+/// handed to the body as one chunk.
+fn stream_prayer_times(
+    state: AppState,
+    headers: HeaderMap,
+    zone: String,
+    tz: chrono_tz::Tz,
+    dates: RangeInclusive<NaiveDate>,
+    adjust: Adjustments,
+    cache_control: &str,
+) -> Result<Response, AppError> {
+    let (from, to) = dates.into_inner();
+    let validators = {
+        let mut conn = state.db_pool.get()?;
+        let (count, last_modified) = select_prayer_times_version(&mut conn, &zone, from, to)?;
+        prayer_times_validators(&zone, tz, from, to, &adjust, count, last_modified)?
+    };
+    if validators.is_not_modified(&headers) {
+        return Ok(validators.not_modified(cache_control));
+    }
+
+    let (tx, rx) = mpsc::channel::<io::Result<Vec<u8>>>(STREAM_BUFFERED_CHUNKS);
+    tokio::task::spawn_blocking(move || {
+        let mut encoder = WaktuSolatEncoder::new(tz, adjust);
+        let result = (|| -> io::Result<()> {
+            let mut start = from;
+            let mut chunk = loop {
+                let page = {
+                    let mut conn = state.db_pool.get().map_err(io::Error::other)?;
+                    select_prayer_times_page(&mut conn, &zone, start, to, STREAM_CHUNK_ROWS as i64)
+                        .map_err(io::Error::other)?
+                };
+                let mut chunk = Vec::new();
+                for pt in &page {
+                    encoder.push(pt, &mut chunk)?;
+                }
+                let next = match page.last() {
+                    Some(last) if page.len() == STREAM_CHUNK_ROWS => last.date.succ_opt().filter(|d| *d <= to),
+                    _ => None,
+                };
+                let Some(next) = next else {
+                    break chunk;
+                };
+                start = next;
+                // The client went away; stop reading
+                if tx.blocking_send(Ok(chunk)).is_err() {
+                    return Ok(());
+                }
+            };
+            encoder.finish(&mut chunk)?;
+            let _ = tx.blocking_send(Ok(chunk));
+            Ok(())
+        })();
+
+        if let Err(e) = result {
+            // Headers are already sent, so abort the body to signal the error
+            tracing::error!("failed streaming prayer times for zone {}: {}", zone, e);
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+
+    let body = Body::from_stream(ReceiverStream::new(rx));
+    Ok(validators.respond(
+        ([(header::CONTENT_TYPE, "application/json")], body),
+        cache_control,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_adjustments() {
+        let adjust: Adjustments = "fajr:+2, maghrib:3,isha:-1".parse().unwrap();
+        assert_eq!(adjust.fajr, 2);
+        assert_eq!(adjust.maghrib, 3);
+        assert_eq!(adjust.isha, -1);
+        assert_eq!(adjust.dhuhr, 0);
+        assert!("".parse::<Adjustments>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_adjustments_rejects_invalid() {
+        assert!("fajr".parse::<Adjustments>().is_err());
+        assert!("fajr:abc".parse::<Adjustments>().is_err());
+        assert!("zuhur:+2".parse::<Adjustments>().is_err());
+        assert!("fajr:+31".parse::<Adjustments>().is_err());
+        assert!("fajr:+1,fajr:+2".parse::<Adjustments>().is_err());
+    }
+
+    fn sample_prayer_time(date: NaiveDate) -> SelectPrayerTime {
+        SelectPrayerTime {
+            id: 1,
+            zone_code: "SGR01".to_string(),
+            date,
+            imsak: NaiveTime::from_hms_opt(5, 55, 0).unwrap(),
+            fajr: NaiveTime::from_hms_opt(6, 5, 0).unwrap(),
+            syuruk: NaiveTime::from_hms_opt(7, 12, 0).unwrap(),
+            dhuhr: NaiveTime::from_hms_opt(13, 20, 0).unwrap(),
+            asr: NaiveTime::from_hms_opt(16, 22, 0).unwrap(),
+            maghrib: NaiveTime::from_hms_opt(19, 23, 0).unwrap(),
+            isha: NaiveTime::from_hms_opt(20, 33, 0).unwrap(),
+            created_at: NaiveDateTime::default(),
+            updated_at: NaiveDateTime::default(),
+        }
+    }
+
+    #[test]
+    fn test_from_prayer_time_applies_adjustments() {
+        let pt = sample_prayer_time(NaiveDate::from_ymd_opt(2026, 4, 1).unwrap());
+        let tz = chrono_tz::Asia::Kuala_Lumpur;
+        let base = WaktuSolat::from_prayer_time(&pt, tz, &Adjustments::default());
+        let adjust: Adjustments = "fajr:+2,maghrib:+3".parse().unwrap();
+        let adjusted = WaktuSolat::from_prayer_time(&pt, tz, &adjust);
+        assert_eq!(adjusted.fajr, base.fajr + 120);
+        assert_eq!(adjusted.maghrib, base.maghrib + 180);
+        assert_eq!(adjusted.isha, base.isha);
+    }
+
+    #[test]
+    fn test_encoder_matches_buffered_response() {
+        let tz = chrono_tz::Asia::Kuala_Lumpur;
+        let start = NaiveDate::from_ymd_opt(2026, 4, 1).unwrap();
+        let pts: Vec<_> = (0..3).map(|i| sample_prayer_time(start + chrono::Duration::days(i))).collect();
+
+        for adjust in [Adjustments::default(), "fajr:+2".parse().unwrap()] {
+            for rows in [&pts[..0], &pts[..]] {
+                let mut encoder = WaktuSolatEncoder::new(tz, adjust.clone());
+                let mut streamed = Vec::new();
+                for pt in rows {
+                    encoder.push(pt, &mut streamed).unwrap();
+                }
+                encoder.finish(&mut streamed).unwrap();
+
+                let buffered = serde_json::to_vec(&WaktuSolatResponse {
+                    data: rows.iter().map(|pt| WaktuSolat::from_prayer_time(pt, tz, &adjust)).collect(),
+                    meta: (!adjust.is_empty()).then_some(WaktuSolatMeta { adjust: adjust.clone() }),
+                })
+                .unwrap();
+                assert_eq!(String::from_utf8(streamed).unwrap(), String::from_utf8(buffered).unwrap());
+            }
+        }
+    }
 }
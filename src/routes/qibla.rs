@@ -0,0 +1,70 @@
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    geodesy,
+    routes::{AppError, ErrorResponse, error::FieldError, extract::Query},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Qibla {
+    pub bearing: f64,     // degrees from true north
+    pub distance_km: f64, // great-circle distance to the Kaaba
+}
+
+impl Qibla {
+    pub fn at(lat: f64, lng: f64) -> Self {
+        Self {
+            bearing: (geodesy::qibla_bearing(lat, lng) * 100.0).round() / 100.0,
+            distance_km: (geodesy::qibla_distance_km(lat, lng) * 10.0).round() / 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct QiblaResponse {
+    pub data: Qibla,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QiblaQuery {
+    /// Latitude in degrees
+    pub lat: f64,
+    /// Longitude in degrees
+    pub lng: f64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/qibla",
+    tag = "qibla",
+    params(QiblaQuery),
+    responses(
+        (status = 200, description = "Qibla bearing and distance", body = QiblaResponse),
+        (status = 400, description = "Coordinates out of range", body = ErrorResponse),
+    )
+)]
+pub async fn get_qibla(Query(params): Query<QiblaQuery>) -> Result<Json<QiblaResponse>, AppError> {
+    if !geodesy::is_valid_coordinate(params.lat, params.lng) {
+        let mut details = Vec::new();
+        if !(-90.0..=90.0).contains(&params.lat) {
+            details.push(FieldError {
+                field: "lat".to_string(),
+                message: "'lat' must be within ±90".to_string(),
+            });
+        }
+        if !(-180.0..=180.0).contains(&params.lng) {
+            details.push(FieldError {
+                field: "lng".to_string(),
+                message: "'lng' must be within ±180".to_string(),
+            });
+        }
+        return Err(AppError::InvalidParameters(details));
+    }
+
+    Ok(Json(QiblaResponse {
+        data: Qibla::at(params.lat, params.lng),
+    }))
+}
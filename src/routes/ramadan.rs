@@ -0,0 +1,185 @@
+use axum::{
+    Json,
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    hijri,
+    routes::{
+        AppError, AppState, ErrorResponse,
+        extract::{Path, Query},
+        formats::{CalendarEvent, Format, csv_response, ics_response, render_csv, render_ics},
+        prayer_times::{Adjustments, WaktuSolat},
+    },
+};
+
+/// Hijri years accepted by the endpoint (roughly 1979–2076).
+const MIN_HIJRI_YEAR: i32 = 1400;
+const MAX_HIJRI_YEAR: i32 = 1500;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RamadanDay {
+    pub day: u32,
+    pub date: NaiveDate,
+    pub zone: String,
+    pub imsak: i64,   // Unix timestamp
+    pub fajr: i64,    // Unix timestamp
+    pub maghrib: i64, // Unix timestamp (iftar)
+    pub isha: i64,    // Unix timestamp
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RamadanMeta {
+    pub hijri_year: i32,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    #[serde(default, skip_serializing_if = "Adjustments::is_empty")]
+    pub adjust: Adjustments,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RamadanResponse {
+    pub data: Vec<RamadanDay>,
+    pub meta: RamadanMeta,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RamadanQuery {
+    /// Response format
+    #[serde(default)]
+    pub format: Format,
+    /// Minute offsets per prayer, e.g. `maghrib:+2` (max ±30)
+    pub adjust: Option<String>,
+}
+
+fn local_time(ts: i64, tz: chrono_tz::Tz) -> String {
+    DateTime::from_timestamp(ts, 0)
+        .unwrap_or_default()
+        .with_timezone(&tz)
+        .format("%H:%M")
+        .to_string()
+}
+
+#[utoipa::path(
+    get,
+    path = "/prayer-times/by-zone/{zone}/ramadan/{hijri_year}",
+    tag = "prayer-times",
+    params(
+        ("zone" = String, Path, description = "Zone code, e.g. `SGR01`"),
+        ("hijri_year" = i32, Path, description = "Hijri year between 1400 and 1500"),
+        RamadanQuery,
+    ),
+    responses(
+        (status = 200, description = "Imsak, fajr, iftar and isha for each day of Ramadan", content(
+            (RamadanResponse = "application/json"),
+            (String = "text/csv"),
+            (String = "text/calendar"),
+        )),
+        (status = 400, description = "Hijri year out of range or invalid adjustment", body = ErrorResponse),
+        (status = 404, description = "Unknown zone", body = ErrorResponse),
+    )
+)]
+pub async fn get_ramadan_times(
+    Path((zone, hijri_year)): Path<(String, i32)>,
+    Query(params): Query<RamadanQuery>,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    if !(MIN_HIJRI_YEAR..=MAX_HIJRI_YEAR).contains(&hijri_year) {
+        return Err(AppError::invalid(
+            "hijri_year",
+            format!("Hijri year must be between {} and {}", MIN_HIJRI_YEAR, MAX_HIJRI_YEAR),
+        ));
+    }
+
+    let adjust = match params.adjust {
+        Some(ref s) => s.parse::<Adjustments>().map_err(|e| AppError::invalid("adjust", e))?,
+        None => Adjustments::default(),
+    };
+
+    tracing::info!("fetching ramadan {} times for zone {}", hijri_year, zone);
+
+    let zone_info = state.cache.zone(&state.db_pool, &zone)?;
+    let zone_info = zone_info.ok_or_else(|| AppError::NotFound(
+        format!("Zone '{}' not found", zone),
+    ))?;
+    let tz = zone_info.timezone();
+
+    let (start, end) = hijri::ramadan_range(hijri_year, &zone_info.country).ok_or_else(|| {
+        AppError::BadRequest(format!("Cannot compute Ramadan for year {}", hijri_year))
+    })?;
+
+    let pts = state.cache.prayer_times(&state.db_pool, &zone, start, end)?;
+    let days: Vec<RamadanDay> = pts
+        .iter()
+        .map(|pt| {
+            let ws = WaktuSolat::from_prayer_time(pt, tz, &adjust);
+            RamadanDay {
+                day: (pt.date - start).num_days() as u32 + 1,
+                date: ws.date,
+                zone: ws.zone,
+                imsak: ws.imsak,
+                fajr: ws.fajr,
+                maghrib: ws.maghrib,
+                isha: ws.isha,
+            }
+        })
+        .collect();
+
+    let filename = format!("ramadan-{}-{}", hijri_year, zone);
+    let response = match params.format {
+        Format::Json => Json(RamadanResponse {
+            data: days,
+            meta: RamadanMeta {
+                hijri_year,
+                start,
+                end,
+                adjust,
+            },
+        })
+        .into_response(),
+        Format::Csv => {
+            let rows: Vec<Vec<String>> = days
+                .iter()
+                .map(|d| {
+                    vec![
+                        d.day.to_string(),
+                        d.date.to_string(),
+                        local_time(d.imsak, tz),
+                        local_time(d.fajr, tz),
+                        local_time(d.maghrib, tz),
+                        local_time(d.isha, tz),
+                    ]
+                })
+                .collect();
+            let header = ["day", "date", "imsak", "fajr", "maghrib", "isha"];
+            csv_response(&filename, render_csv(&header, &rows))
+        }
+        Format::Ics => {
+            let events: Vec<CalendarEvent> = days
+                .iter()
+                .flat_map(|d| {
+                    [
+                        ("imsak", "Imsak", d.imsak),
+                        ("fajr", "Fajr", d.fajr),
+                        ("maghrib", "Iftar (Maghrib)", d.maghrib),
+                        ("isha", "Isha", d.isha),
+                    ]
+                    .map(|(key, label, start)| CalendarEvent {
+                        uid: format!("{}-{}-{}@simplesolat", d.zone, d.date, key),
+                        summary: format!("Ramadan {} – {}", d.day, label),
+                        start,
+                    })
+                })
+                .collect();
+            let name = format!("Ramadan {} AH – {}", hijri_year, zone);
+            ics_response(&filename, render_ics(&name, &events))
+        }
+    };
+
+    Ok(response)
+}
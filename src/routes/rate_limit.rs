@@ -0,0 +1,187 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderMap, HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{NaiveDate, Utc};
+
+use crate::{
+    models::zones::UpsertZone,
+    routes::{
+        AppError, AppState,
+        access::{api_key, peer_ip},
+        extract::DateSpec,
+        prayer_times::resolve_range,
+    },
+    service::{api_keys::Decision, rate_limit::RateLimitStatus},
+};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+const RATELIMIT_POLICY: HeaderName = HeaderName::from_static("ratelimit-policy");
+
+/// Prayer time requests cost one unit per month of data.
+const DAYS_PER_UNIT: usize = 31;
+/// Coverage requests cost one unit per this many zones.
+const ZONES_PER_UNIT: usize = 20;
+
+fn query_param<'a>(query: &'a str, name: &str) -> Option<std::borrow::Cow<'a, str>> {
+    form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value)
+}
+
+/// Number of days a prayer times request covers, resolved as the handler
+/// does. Unparseable dates fall back to the defaults; the handler rejects
+/// them anyway.
+fn requested_days(query: &str, today: NaiveDate) -> i64 {
+    let spec = |name| query_param(query, name).and_then(|v| v.parse::<DateSpec>().ok());
+    resolve_range(spec("from"), spec("to"), today)
+        .map_or(1, |dates| (*dates.end() - *dates.start()).num_days() + 1)
+        .max(1)
+}
+
+/// Cost of a request in rate limit units, by route. Only zones already in
+/// the read cache are consulted, so pricing a request never touches the
+/// database; until they are loaded, dates resolve in UTC and coverage costs
+/// one unit.
+fn request_cost(state: &AppState, route: &str, path: &str, query: &str) -> f64 {
+    let route = route
+        .strip_prefix("/v1")
+        .or_else(|| route.strip_prefix("/v2"))
+        .unwrap_or(route);
+    let zones = state.cache.cached_zones();
+
+    let units = match route {
+        "/prayer-times/by-zone/{zone}" => {
+            let zone = path.rsplit('/').next().unwrap_or_default();
+            let tz = zones
+                .as_ref()
+                .and_then(|zones| zones.items.iter().find(|z| z.zone_code == zone))
+                .map_or(chrono_tz::UTC, UpsertZone::timezone);
+            let days = requested_days(query, Utc::now().with_timezone(&tz).date_naive());
+            (days as usize).div_ceil(DAYS_PER_UNIT)
+        }
+        "/coverage" => {
+            let country = query_param(query, "country");
+            let zones = zones.map_or(0, |zones| {
+                zones
+                    .items
+                    .iter()
+                    .filter(|z| country.as_deref().is_none_or(|c| z.country == c))
+                    .count()
+            });
+            zones.div_ceil(ZONES_PER_UNIT)
+        }
+        _ => 1,
+    };
+    units.max(1) as f64
+}
+
+fn set_headers(headers: &mut HeaderMap, status: &RateLimitStatus, window: &str) {
+    let reset = status.reset_after.as_secs_f64().ceil() as u64;
+    for (name, value) in [
+        (RATELIMIT_LIMIT, status.limit.to_string()),
+        (RATELIMIT_REMAINING, status.remaining.to_string()),
+        (RATELIMIT_RESET, reset.to_string()),
+        (RATELIMIT_POLICY, window.to_string()),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+/// 429 carrying the state of the bucket that refused the request.
+fn limited(status: &RateLimitStatus, retry_after: Duration, window: &str) -> Response {
+    let mut response = AppError::RateLimited(retry_after.as_secs().max(1)).into_response();
+    set_headers(response.headers_mut(), status, window);
+    response
+}
+
+/// Middleware enforcing request limits. A request with an API key is charged
+/// against that key's rate and daily quota only; one without a key, or with
+/// an unknown or revoked key, against its client IP. An invalid key is
+/// rejected rather than downgraded to anonymous access. Expensive routes cost
+/// more than one unit either way.
+pub async fn rate_limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let limiter = &state.rate_limiter;
+    let forwarded_for = request
+        .headers()
+        .get(X_FORWARDED_FOR)
+        .and_then(|v| v.to_str().ok());
+    let ip = limiter.policy().client_ip(peer_ip(&request), forwarded_for);
+
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+    let uri = request.uri();
+    let cost = request_cost(&state, &route, uri.path(), uri.query().unwrap_or_default());
+    let window = format!("{};w=60", limiter.policy().units_per_minute);
+
+    if let Some(key) = api_key(request.headers()).map(str::to_string) {
+        let decision = match state.access.check_key(&state.db_pool, &key, cost) {
+            Ok(decision) => decision,
+            Err(e) => return AppError::from(e).into_response(),
+        };
+        return match decision {
+            Decision::Allowed(status) => {
+                let mut response = next.run(request).await;
+                set_headers(response.headers_mut(), &status, &format!("{};w=60", status.limit));
+                response
+            }
+            Decision::RateLimited { status, retry_after } => {
+                limited(&status, retry_after, &format!("{};w=60", status.limit))
+            }
+            Decision::QuotaExceeded { retry_after } => {
+                AppError::QuotaExceeded(retry_after.as_secs().max(1)).into_response()
+            }
+            // Charged to the IP like anonymous requests, so guessing keys is
+            // limited too
+            Decision::InvalidKey => match limiter.check(ip, cost) {
+                Ok(_) => AppError::Unauthorized("Invalid or revoked API key".to_string()).into_response(),
+                Err((status, retry_after)) => limited(&status, retry_after, &window),
+            },
+        };
+    }
+
+    match limiter.check(ip, cost) {
+        Ok(status) => {
+            let mut response = next.run(request).await;
+            set_headers(response.headers_mut(), &status, &window);
+            response
+        }
+        Err((status, retry_after)) => {
+            tracing::debug!("rate limited {} on {} (cost {})", ip, route, cost);
+            limited(&status, retry_after, &window)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_requested_days() {
+        let today = date(2026, 10, 19);
+        assert_eq!(requested_days("", today), 31);
+        assert_eq!(requested_days("from=2026-02", today), 28);
+        assert_eq!(requested_days("from=2026-01-01&to=2027-12-31", today), 730);
+        assert_eq!(requested_days("from=today&to=%2B6d", today), 7);
+        assert_eq!(requested_days("from=2026-12-01&to=2026-01-01", today), 1);
+        assert_eq!(requested_days("from=soon", today), 31);
+    }
+}
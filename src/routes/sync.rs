@@ -0,0 +1,29 @@
+use axum::{Json, extract::State};
+use chrono::Utc;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    routes::{AppError, AppState, ErrorResponse},
+    service::schedule::{ScheduledSync, load_plan},
+};
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncScheduleResponse {
+    pub data: Vec<ScheduledSync>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/sync/schedule",
+    tag = "health",
+    responses(
+        (status = 200, description = "Configured sync schedules with last and next run times; empty when none are configured", body = SyncScheduleResponse),
+        (status = 503, description = "Database is unreachable", body = ErrorResponse),
+    )
+)]
+pub async fn get_sync_schedule(State(state): State<AppState>) -> Result<Json<SyncScheduleResponse>, AppError> {
+    let mut conn = state.db_pool.get()?;
+    let data = load_plan(&mut conn, &state.sync_schedules, Utc::now().naive_utc())?;
+    Ok(Json(SyncScheduleResponse { data }))
+}
@@ -0,0 +1,81 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use chrono::NaiveDate;
+
+use crate::routes::caching::HTTP_DATE_FORMAT;
+
+/// Prefix of the current stable API version.
+pub const CURRENT_VERSION: &str = "/v1";
+
+/// Deprecation schedule for a set of routes: clients are warned from `since`
+/// and pointed at the same path under `successor` until `sunset`.
+#[derive(Debug, Clone, Copy)]
+pub struct Deprecation {
+    pub since: NaiveDate,
+    pub sunset: NaiveDate,
+    pub successor: &'static str,
+}
+
+/// The original unprefixed routes, kept as aliases of v1.
+pub const UNVERSIONED: Deprecation = Deprecation {
+    since: NaiveDate::from_ymd_opt(2026, 10, 19).unwrap(),
+    sunset: NaiveDate::from_ymd_opt(2027, 10, 19).unwrap(),
+    successor: CURRENT_VERSION,
+};
+
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+const SUNSET: HeaderName = HeaderName::from_static("sunset");
+const LINK: HeaderName = HeaderName::from_static("link");
+
+impl Deprecation {
+    /// `Deprecation` (RFC 9745), `Sunset` (RFC 8594) and `Link` header values.
+    fn headers(&self, path_and_query: &str) -> [(HeaderName, String); 3] {
+        let midnight = |d: NaiveDate| d.and_hms_opt(0, 0, 0).expect("invalid midnight");
+        [
+            (DEPRECATION, format!("@{}", midnight(self.since).and_utc().timestamp())),
+            (SUNSET, midnight(self.sunset).format(HTTP_DATE_FORMAT).to_string()),
+            (
+                LINK,
+                format!("<{}{}>; rel=\"successor-version\"", self.successor, path_and_query),
+            ),
+        ]
+    }
+}
+
+/// Middleware adding the deprecation headers to every response.
+pub async fn deprecation_headers(
+    State(deprecation): State<Deprecation>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path_and_query = request
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_default();
+
+    let mut response = next.run(request).await;
+    for (name, value) in deprecation.headers(&path_and_query) {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deprecation_header_values() {
+        let [(_, deprecation), (_, sunset), (_, link)] = UNVERSIONED.headers("/zones?country=MY");
+        assert_eq!(deprecation, "@1792368000");
+        assert_eq!(sunset, "Tue, 19 Oct 2027 00:00:00 GMT");
+        assert_eq!(link, "</v1/zones?country=MY>; rel=\"successor-version\"");
+    }
+}
@@ -1,18 +1,42 @@
-use axum::{Json, extract::{Query, State}};
+use axum::{
+    Json,
+    extract::State,
+    http::HeaderMap,
+    response::Response,
+};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    models::zones::{UpsertZone, select_zones, select_zones_by_country},
-    routes::{AppError, AppState},
+    models::{
+        prayer_times::select_date_range_for_zone,
+        zones::{UpsertZone, select_zone_by_code},
+    },
+    routes::{
+        AppError, AppState, ErrorResponse,
+        extract::{Path, Query},
+        caching::{LIST_CACHE_CONTROL, cached_json},
+        qibla::Qibla,
+    },
+    service::search::Highlight,
 };
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Upper bound for the `limit` search parameter.
+const MAX_SEARCH_LIMIT: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Zone {
     pub zone: String,
     pub country: String,
     pub state: String,
     pub location: String,
     pub timezone: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// `[min_lng, min_lat, max_lng, max_lat]`, as in GeoJSON
+    pub bbox: Option<[f64; 4]>,
+    pub qibla: Option<Qibla>,
 }
 
 impl From<&UpsertZone> for Zone {
@@ -23,35 +47,207 @@ impl From<&UpsertZone> for Zone {
             state: value.state.to_string(),
             location: value.location.to_string(),
             timezone: value.timezone.to_string(),
+            latitude: value.latitude,
+            longitude: value.longitude,
+            bbox: match (
+                value.min_longitude,
+                value.min_latitude,
+                value.max_longitude,
+                value.max_latitude,
+            ) {
+                (Some(min_lng), Some(min_lat), Some(max_lng), Some(max_lat)) => {
+                    Some([min_lng, min_lat, max_lng, max_lat])
+                }
+                _ => None,
+            },
+            qibla: match (value.latitude, value.longitude) {
+                (Some(lat), Some(lng)) => Some(Qibla::at(lat, lng)),
+                _ => None,
+            },
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct ZonesResponse {
     pub data: Vec<Zone>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Range of dates with stored prayer times (`null` when there are none).
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Coverage {
+    pub first_date: Option<NaiveDate>,
+    pub last_date: Option<NaiveDate>,
+}
+
+impl From<(Option<NaiveDate>, Option<NaiveDate>)> for Coverage {
+    fn from((first_date, last_date): (Option<NaiveDate>, Option<NaiveDate>)) -> Self {
+        Self { first_date, last_date }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ZoneDetail {
+    #[serde(flatten)]
+    pub zone: Zone,
+    pub coverage: Coverage,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ZoneDetailResponse {
+    pub data: ZoneDetail,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ZonesQuery {
+    /// Only zones of this country, e.g. `MY`
     pub country: Option<String>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/zones",
+    tag = "zones",
+    params(ZonesQuery),
+    responses(
+        (status = 200, description = "All zones", body = ZonesResponse),
+        (status = 304, description = "Client copy is current"),
+    )
+)]
 pub async fn get_zones(
     Query(params): Query<ZonesQuery>,
     State(state): State<AppState>,
-) -> Result<Json<ZonesResponse>, AppError> {
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
     tracing::info!("fetching zones");
 
+    let zones = state.cache.zones(&state.db_pool)?;
+
+    let response = ZonesResponse {
+        data: zones
+            .items
+            .iter()
+            .filter(|z| params.country.as_ref().is_none_or(|c| z.country == *c))
+            .map(|z| z.into())
+            .collect(),
+    };
+
+    cached_json(&headers, &response, zones.last_modified, LIST_CACHE_CONTROL)
+}
+
+#[utoipa::path(
+    get,
+    path = "/zones/{zone}",
+    tag = "zones",
+    params(("zone" = String, Path, description = "Zone code, e.g. `SGR01`")),
+    responses(
+        (status = 200, description = "Zone with data coverage", body = ZoneDetailResponse),
+        (status = 404, description = "Unknown zone", body = ErrorResponse),
+    )
+)]
+pub async fn get_zone(
+    Path(zone): Path<String>,
+    State(state): State<AppState>,
+) -> Result<Json<ZoneDetailResponse>, AppError> {
+    tracing::info!("fetching zone {}", zone);
+
     let mut conn = state.db_pool.get()?;
 
-    let zones = match params.country {
-        Some(ref country) => select_zones_by_country(&mut conn, country)?,
-        None => select_zones(&mut conn)?,
+    let zone_info = select_zone_by_code(&mut conn, &zone)?;
+    let zone_info = zone_info.ok_or_else(|| AppError::NotFound(
+        format!("Zone '{}' not found", zone),
+    ))?;
+    let coverage = select_date_range_for_zone(&mut conn, &zone)?;
+
+    let response = ZoneDetailResponse {
+        data: ZoneDetail {
+            zone: (&zone_info).into(),
+            coverage: coverage.into(),
+        },
     };
 
-    let response = ZonesResponse {
-        data: zones.iter().map(|z| z.into()).collect(),
+    Ok(Json(response))
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ZoneHighlight {
+    pub field: String,
+    pub start: usize, // character offset in the field value
+    pub end: usize,
+}
+
+impl From<&Highlight> for ZoneHighlight {
+    fn from(value: &Highlight) -> Self {
+        Self {
+            field: value.field.as_str().to_string(),
+            start: value.start,
+            end: value.end,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ZoneSearchResult {
+    #[serde(flatten)]
+    pub zone: Zone,
+    pub score: f64,
+    pub highlights: Vec<ZoneHighlight>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ZoneSearchResponse {
+    pub data: Vec<ZoneSearchResult>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ZoneSearchQuery {
+    /// Search text, matched against zone code, state and location
+    pub q: String,
+    /// Only zones of this country, e.g. `MY`
+    pub country: Option<String>,
+    /// Maximum number of results (default 20, max 100)
+    pub limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/zones/search",
+    tag = "zones",
+    params(ZoneSearchQuery),
+    responses(
+        (status = 200, description = "Zones ranked by relevance", body = ZoneSearchResponse),
+        (status = 400, description = "Empty query", body = ErrorResponse),
+    )
+)]
+pub async fn search_zones(
+    Query(params): Query<ZoneSearchQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ZoneSearchResponse>, AppError> {
+    let q = params.q.trim();
+    if q.is_empty() {
+        return Err(AppError::invalid("q", "'q' must not be empty"));
+    }
+    let limit = params.limit.unwrap_or(20).clamp(1, MAX_SEARCH_LIMIT);
+
+    tracing::info!("searching zones for '{}'", q);
+
+    let index = state
+        .zone_search
+        .index(|| state.cache.zones(&state.db_pool).map(|zones| zones.items.clone()))
+        .await?;
+
+    let response = ZoneSearchResponse {
+        data: index
+            .search(q, params.country.as_deref(), limit)
+            .iter()
+            .map(|hit| ZoneSearchResult {
+                zone: hit.zone.into(),
+                score: (hit.score * 1000.0).round() / 1000.0,
+                highlights: hit.highlights.iter().map(|h| h.into()).collect(),
+            })
+            .collect(),
     };
 
     Ok(Json(response))
@@ -1,5 +1,30 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_key_usage (api_key_id, day) {
+        api_key_id -> Int8,
+        day -> Date,
+        requests -> Int8,
+    }
+}
+
+diesel::table! {
+    api_keys (id) {
+        id -> Int8,
+        #[max_length = 100]
+        name -> Varchar,
+        #[max_length = 16]
+        key_prefix -> Varchar,
+        #[max_length = 64]
+        key_hash -> Varchar,
+        requests_per_minute -> Int4,
+        daily_quota -> Nullable<Int8>,
+        created_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+        admin -> Bool,
+    }
+}
+
 diesel::table! {
     countries (code) {
         #[max_length = 2]
@@ -13,6 +38,7 @@ diesel::table! {
         #[max_length = 20]
         shape_property -> Varchar,
         created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -30,6 +56,16 @@ diesel::table! {
         maghrib -> Time,
         isha -> Time,
         created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    sync_runs (country) {
+        #[max_length = 2]
+        country -> Varchar,
+        started_at -> Timestamp,
+        finished_at -> Timestamp,
     }
 }
 
@@ -45,7 +81,16 @@ diesel::table! {
         country -> Varchar,
         #[max_length = 40]
         timezone -> Varchar,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
+        min_latitude -> Nullable<Float8>,
+        min_longitude -> Nullable<Float8>,
+        max_latitude -> Nullable<Float8>,
+        max_longitude -> Nullable<Float8>,
+        updated_at -> Timestamp,
     }
 }
 
-diesel::allow_tables_to_appear_in_same_query!(countries, prayer_times, zones,);
+diesel::joinable!(api_key_usage -> api_keys (api_key_id));
+
+diesel::allow_tables_to_appear_in_same_query!(api_key_usage, api_keys, countries, prayer_times, sync_runs, zones,);
@@ -0,0 +1,293 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{NaiveDate, Utc};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    models::{
+        api_keys::{
+            ApiKey, NewApiKey, add_api_key_usage, insert_api_key, select_active_api_key_by_hash,
+            select_api_key_usage,
+        },
+        db::DbPool,
+    },
+    service::{
+        cache::CacheError,
+        rate_limit::{RateLimitStatus, TokenBucket},
+    },
+};
+
+/// Every generated key starts with this, so leaked keys are easy to grep for.
+pub const KEY_PREFIX: &str = "ss_";
+
+/// Characters of a key kept in clear text to identify it in listings.
+const VISIBLE_KEY_CHARS: usize = 11;
+
+/// Default limit for new keys.
+pub const DEFAULT_REQUESTS_PER_MINUTE: i32 = 600;
+
+/// How often usage counters are written to the database.
+const USAGE_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a key record is trusted before re-reading it, so revocations and
+/// limit changes take effect without a restart.
+const KEY_TTL: Duration = Duration::from_secs(60);
+
+/// Most unknown keys remembered at once. Beyond this, unknown keys are looked
+/// up on every request, which the per-IP limit still bounds.
+const MAX_UNKNOWN_KEYS: usize = 10_000;
+
+/// Creates a random key. Only its hash is stored.
+pub fn generate_key() -> String {
+    format!("{}{}", KEY_PREFIX, uuid::Uuid::new_v4().simple())
+}
+
+pub fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Stores a new key and returns it with its plain-text value, which cannot be
+/// recovered later.
+pub fn create_key(
+    conn: &mut diesel::PgConnection,
+    name: &str,
+    requests_per_minute: i32,
+    daily_quota: Option<i64>,
+    admin: bool,
+) -> Result<(String, ApiKey), diesel::result::Error> {
+    let key = generate_key();
+    let record = insert_api_key(
+        conn,
+        &NewApiKey {
+            name: name.to_string(),
+            key_prefix: key.chars().take(VISIBLE_KEY_CHARS).collect(),
+            key_hash: hash_key(&key),
+            requests_per_minute,
+            daily_quota,
+            admin,
+        },
+    )?;
+    Ok((key, record))
+}
+
+/// Outcome of checking a request against its client's limits.
+#[derive(Debug)]
+pub enum Decision {
+    /// With the key's bucket after charging the request.
+    Allowed(RateLimitStatus),
+    InvalidKey,
+    RateLimited { status: RateLimitStatus, retry_after: Duration },
+    QuotaExceeded { retry_after: Duration },
+}
+
+struct KeyState {
+    key: ApiKey,
+    loaded_at: Instant,
+    bucket: TokenBucket,
+    day: NaiveDate,
+    /// Requests today, including those not yet written to the database.
+    used: i64,
+    pending: i64,
+}
+
+/// Per-key limits: each key carries its own rate and daily quota. Usage counters are kept in memory and
+/// written to `api_key_usage` by [`AccessControl::flush_usage`].
+#[derive(Default)]
+pub struct AccessControl {
+    keys: Mutex<HashMap<String, KeyState>>,
+    /// Hashes with no active key, and when they were looked up.
+    unknown_keys: Mutex<HashMap<String, Instant>>,
+    /// Pending counts from previous days, kept until flushed.
+    carried_over: Mutex<Vec<(i64, NaiveDate, i64)>>,
+}
+
+fn until_midnight_utc() -> Duration {
+    let now = Utc::now();
+    let midnight = (now.date_naive() + chrono::Duration::days(1))
+        .and_hms_opt(0, 0, 0)
+        .expect("invalid midnight")
+        .and_utc();
+    (midnight - now).to_std().unwrap_or_default()
+}
+
+impl AccessControl {
+    pub fn check_key(&self, pool: &DbPool, raw_key: &str, cost: f64) -> Result<Decision, CacheError> {
+        let hash = hash_key(raw_key);
+        let today = Utc::now().date_naive();
+
+        let fresh = {
+            let keys = self.keys.lock().unwrap();
+            keys.get(&hash).is_some_and(|s| s.loaded_at.elapsed() < KEY_TTL && s.day == today)
+        };
+        if !fresh {
+            let known_unknown = {
+                let unknown = self.unknown_keys.lock().unwrap();
+                unknown.get(&hash).is_some_and(|at| at.elapsed() < KEY_TTL)
+            };
+            if known_unknown {
+                return Ok(Decision::InvalidKey);
+            }
+            let mut conn = pool.get()?;
+            let Some(key) = select_active_api_key_by_hash(&mut conn, &hash)? else {
+                self.forget(hash);
+                return Ok(Decision::InvalidKey);
+            };
+            let stored = select_api_key_usage(&mut conn, key.id, today)?;
+            self.refresh(hash.clone(), key, stored, today);
+        }
+
+        let now = Instant::now();
+        let mut keys = self.keys.lock().unwrap();
+        let Some(state) = keys.get_mut(&hash) else {
+            return Ok(Decision::InvalidKey);
+        };
+        if state.key.daily_quota.is_some_and(|quota| state.used >= quota) {
+            return Ok(Decision::QuotaExceeded {
+                retry_after: until_midnight_utc(),
+            });
+        }
+        if let Err(retry_after) = state.bucket.try_take(cost, now) {
+            return Ok(Decision::RateLimited {
+                status: state.bucket.status(),
+                retry_after,
+            });
+        }
+        state.used += 1;
+        state.pending += 1;
+        Ok(Decision::Allowed(state.bucket.status()))
+    }
+
+    fn refresh(&self, hash: String, key: ApiKey, stored: i64, today: NaiveDate) {
+        let now = Instant::now();
+        let mut keys = self.keys.lock().unwrap();
+        match keys.get_mut(&hash) {
+            Some(state) => {
+                if state.day != today {
+                    if state.pending > 0 {
+                        self.carried_over.lock().unwrap().push((state.key.id, state.day, state.pending));
+                    }
+                    state.pending = 0;
+                    state.day = today;
+                }
+                // Limits may have changed; keep the bucket unless they did
+                if state.key.requests_per_minute != key.requests_per_minute {
+                    state.bucket = TokenBucket::per_minute(key.requests_per_minute, now);
+                }
+                state.used = stored + state.pending;
+                state.key = key;
+                state.loaded_at = now;
+            }
+            None => {
+                let bucket = TokenBucket::per_minute(key.requests_per_minute, now);
+                keys.insert(
+                    hash,
+                    KeyState {
+                        key,
+                        loaded_at: now,
+                        bucket,
+                        day: today,
+                        used: stored,
+                        pending: 0,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Records a hash with no active key, keeping usage of a key that was
+    /// just revoked for the next flush.
+    fn forget(&self, hash: String) {
+        if let Some(state) = self.keys.lock().unwrap().remove(&hash)
+            && state.pending > 0
+        {
+            self.carried_over.lock().unwrap().push((state.key.id, state.day, state.pending));
+        }
+
+        let mut unknown = self.unknown_keys.lock().unwrap();
+        if unknown.len() >= MAX_UNKNOWN_KEYS {
+            unknown.retain(|_, at| at.elapsed() < KEY_TTL);
+        }
+        if unknown.len() < MAX_UNKNOWN_KEYS {
+            unknown.insert(hash, Instant::now());
+        }
+    }
+
+    /// Writes pending usage to the database and forgets idle keys.
+    pub fn flush_usage(&self, pool: &DbPool) -> Result<(), CacheError> {
+        let mut batch = std::mem::take(&mut *self.carried_over.lock().unwrap());
+        {
+            let mut keys = self.keys.lock().unwrap();
+            for state in keys.values_mut() {
+                if state.pending > 0 {
+                    batch.push((state.key.id, state.day, state.pending));
+                    state.pending = 0;
+                }
+            }
+            keys.retain(|_, s| s.loaded_at.elapsed() < KEY_TTL * 10);
+        }
+        self.unknown_keys.lock().unwrap().retain(|_, at| at.elapsed() < KEY_TTL);
+
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let mut conn = match pool.get() {
+            Ok(conn) => conn,
+            Err(e) => {
+                self.carried_over.lock().unwrap().extend(batch);
+                return Err(e.into());
+            }
+        };
+        for (i, &(id, day, requests)) in batch.iter().enumerate() {
+            if let Err(e) = add_api_key_usage(&mut conn, id, day, requests) {
+                self.carried_over.lock().unwrap().extend_from_slice(&batch[i..]);
+                return Err(e.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Periodically writes usage counters on a background task.
+pub fn spawn_usage_flusher(access: Arc<AccessControl>, pool: DbPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(USAGE_FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let (access, pool) = (access.clone(), pool.clone());
+            if let Ok(Err(e)) = tokio::task::spawn_blocking(move || access.flush_usage(&pool)).await {
+                tracing::error!("failed to record API key usage: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_keys_are_unique_and_prefixed() {
+        let (a, b) = (generate_key(), generate_key());
+        assert!(a.starts_with(KEY_PREFIX));
+        assert_ne!(a, b);
+        assert_eq!(hash_key(&a), hash_key(&a));
+        assert_eq!(hash_key(&a).len(), 64);
+    }
+
+    #[test]
+    fn test_unknown_keys_are_capped() {
+        let access = AccessControl::default();
+        for i in 0..MAX_UNKNOWN_KEYS + 100 {
+            access.forget(hash_key(&i.to_string()));
+        }
+        assert_eq!(access.unknown_keys.lock().unwrap().len(), MAX_UNKNOWN_KEYS);
+        assert!(access.keys.lock().unwrap().is_empty());
+    }
+}
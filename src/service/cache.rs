@@ -0,0 +1,451 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::{Datelike, Months, NaiveDate, NaiveDateTime};
+use diesel::{Connection, PgConnection, RunQueryDsl};
+use serde::Serialize;
+
+use crate::models::{
+    countries::{UpsertCountry, select_countries, select_countries_last_modified},
+    db::{DbPool, SYNC_CHANNEL},
+    prayer_times::{SelectPrayerTime, select_prayer_times_for_zone},
+    zones::{UpsertZone, select_zones, select_zones_last_modified},
+};
+
+/// Month blocks kept in memory (~600 zones × 6 months).
+const PRAYER_TIME_BLOCKS: usize = 4096;
+
+/// How often the listener polls its connection for notifications.
+const LISTEN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error(transparent)]
+    Pool(#[from] diesel::r2d2::PoolError),
+    #[error(transparent)]
+    Db(#[from] diesel::result::Error),
+}
+
+#[derive(Default)]
+pub struct CacheStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheStatsSnapshot {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> CacheStatsSnapshot {
+        CacheStatsSnapshot {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Least-recently-used map; `order` indexes entries by their last access.
+struct Lru<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (u64, V)>,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K: Clone + Eq + Hash, V: Clone> Lru<K, V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<V> {
+        self.tick += 1;
+        let (used, value) = self.entries.get_mut(key)?;
+        self.order.remove(used);
+        *used = self.tick;
+        self.order.insert(self.tick, key.clone());
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.tick += 1;
+        if let Some((used, _)) = self.entries.remove(&key) {
+            self.order.remove(&used);
+        }
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (self.tick, value));
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// A fully cached table with its newest `updated_at`.
+pub struct CachedList<T> {
+    pub items: Vec<T>,
+    pub last_modified: Option<NaiveDateTime>,
+}
+
+type MonthKey = (String, i32, u32);
+
+fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).expect("invalid month start")
+}
+
+fn month_end(start: NaiveDate) -> NaiveDate {
+    start.checked_add_months(Months::new(1)).expect("date overflow adding 1 month") - chrono::Duration::days(1)
+}
+
+/// In-process read cache for data that only changes on sync: zones and
+/// countries are held in full, prayer times as per-zone month blocks.
+/// Cleared whenever a sync announces changes on [`SYNC_CHANNEL`].
+pub struct ReadCache {
+    /// Bumped by `invalidate`; rows loaded under an older generation may
+    /// predate the sync and are returned but not kept
+    generation: AtomicU64,
+    zones: RwLock<Option<Arc<CachedList<UpsertZone>>>>,
+    countries: RwLock<Option<Arc<CachedList<UpsertCountry>>>>,
+    prayer_times: Mutex<Lru<MonthKey, Arc<Vec<SelectPrayerTime>>>>,
+    pub zones_stats: CacheStats,
+    pub countries_stats: CacheStats,
+    pub prayer_times_stats: CacheStats,
+}
+
+impl Default for ReadCache {
+    fn default() -> Self {
+        Self {
+            generation: AtomicU64::new(0),
+            zones: RwLock::default(),
+            countries: RwLock::default(),
+            prayer_times: Mutex::new(Lru::new(PRAYER_TIME_BLOCKS)),
+            zones_stats: CacheStats::default(),
+            countries_stats: CacheStats::default(),
+            prayer_times_stats: CacheStats::default(),
+        }
+    }
+}
+
+/// Returns the cached list in `slot`, or loads and caches it unless the
+/// cache was invalidated while loading.
+fn cached_list<T>(
+    generation: &AtomicU64,
+    slot: &RwLock<Option<Arc<CachedList<T>>>>,
+    stats: &CacheStats,
+    load: impl FnOnce() -> Result<CachedList<T>, CacheError>,
+) -> Result<Arc<CachedList<T>>, CacheError> {
+    if let Some(ref list) = *slot.read().unwrap() {
+        stats.hit();
+        return Ok(list.clone());
+    }
+    stats.miss();
+
+    let loaded_in = generation.load(Ordering::SeqCst);
+    let list = Arc::new(load()?);
+    let mut current = slot.write().unwrap();
+    if generation.load(Ordering::SeqCst) == loaded_in {
+        *current = Some(list.clone());
+    }
+    Ok(list)
+}
+
+impl ReadCache {
+    pub fn zones(&self, pool: &DbPool) -> Result<Arc<CachedList<UpsertZone>>, CacheError> {
+        cached_list(&self.generation, &self.zones, &self.zones_stats, || {
+            let mut conn = pool.get()?;
+            Ok(CachedList {
+                items: select_zones(&mut conn)?,
+                last_modified: select_zones_last_modified(&mut conn, None)?,
+            })
+        })
+    }
+
+    /// Zones if already cached, without loading them.
+    pub fn cached_zones(&self) -> Option<Arc<CachedList<UpsertZone>>> {
+        self.zones.read().unwrap().clone()
+    }
+
+    pub fn zone(&self, pool: &DbPool, zone_code: &str) -> Result<Option<UpsertZone>, CacheError> {
+        let zones = self.zones(pool)?;
+        Ok(zones.items.iter().find(|z| z.zone_code == zone_code).cloned())
+    }
+
+    pub fn countries(&self, pool: &DbPool) -> Result<Arc<CachedList<UpsertCountry>>, CacheError> {
+        cached_list(&self.generation, &self.countries, &self.countries_stats, || {
+            let mut conn = pool.get()?;
+            Ok(CachedList {
+                items: select_countries(&mut conn)?,
+                last_modified: select_countries_last_modified(&mut conn)?,
+            })
+        })
+    }
+
+    /// Prayer times for a zone between `from` and `to` (inclusive), served
+    /// from month blocks. Missing months are loaded with a single query.
+    pub fn prayer_times(
+        &self,
+        pool: &DbPool,
+        zone_code: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<SelectPrayerTime>, CacheError> {
+        self.prayer_times_with(zone_code, from, to, |first, last| {
+            let mut conn = pool.get()?;
+            Ok(select_prayer_times_for_zone(&mut conn, zone_code, first, last)?)
+        })
+    }
+
+    /// [`ReadCache::prayer_times`], with missing months loaded by `load`
+    /// (given the first and last day to load).
+    fn prayer_times_with(
+        &self,
+        zone_code: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+        load: impl FnOnce(NaiveDate, NaiveDate) -> Result<Vec<SelectPrayerTime>, CacheError>,
+    ) -> Result<Vec<SelectPrayerTime>, CacheError> {
+        let mut months = Vec::new();
+        let mut cursor = month_start(from);
+        while cursor <= to {
+            months.push(cursor);
+            cursor = cursor.checked_add_months(Months::new(1)).expect("date overflow adding 1 month");
+        }
+
+        let key = |m: NaiveDate| (zone_code.to_string(), m.year(), m.month());
+        let mut blocks: Vec<Option<Arc<Vec<SelectPrayerTime>>>> = {
+            let mut lru = self.prayer_times.lock().unwrap();
+            months.iter().map(|m| lru.get(&key(*m))).collect()
+        };
+
+        let missing: Vec<NaiveDate> = months
+            .iter()
+            .zip(&blocks)
+            .filter(|(_, b)| b.is_none())
+            .map(|(m, _)| *m)
+            .collect();
+        for _ in 0..months.len() - missing.len() {
+            self.prayer_times_stats.hit();
+        }
+
+        if let (Some(first), Some(last)) = (missing.first(), missing.last()) {
+            for _ in &missing {
+                self.prayer_times_stats.miss();
+            }
+            let loaded_in = self.generation.load(Ordering::SeqCst);
+            let rows = load(*first, month_end(*last))?;
+
+            let mut by_month: HashMap<NaiveDate, Vec<SelectPrayerTime>> = HashMap::new();
+            for row in rows {
+                by_month.entry(month_start(row.date)).or_default().push(row);
+            }
+
+            let mut lru = self.prayer_times.lock().unwrap();
+            let current = self.generation.load(Ordering::SeqCst) == loaded_in;
+            for (month, block) in months.iter().zip(blocks.iter_mut()) {
+                if block.is_none() {
+                    let loaded = Arc::new(by_month.remove(month).unwrap_or_default());
+                    if current {
+                        lru.insert(key(*month), loaded.clone());
+                    }
+                    *block = Some(loaded);
+                }
+            }
+        }
+
+        Ok(blocks
+            .iter()
+            .flatten()
+            .flat_map(|b| b.iter())
+            .filter(|pt| pt.date >= from && pt.date <= to)
+            .cloned()
+            .collect())
+    }
+
+    /// Number of month blocks currently held.
+    pub fn prayer_time_blocks(&self) -> usize {
+        self.prayer_times.lock().unwrap().len()
+    }
+
+    /// Drops everything so the next reads go to the database. Loads already
+    /// running are not cached.
+    pub fn invalidate(&self) {
+        // Bumped before clearing, so a load that stores after the clear
+        // always sees the new generation
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *self.zones.write().unwrap() = None;
+        *self.countries.write().unwrap() = None;
+        self.prayer_times.lock().unwrap().clear();
+    }
+}
+
+/// Runs `on_change` whenever a sync process signals new data on
+/// [`SYNC_CHANNEL`]. Uses a dedicated connection on a background thread and
+/// reconnects on failure, invoking `on_change` after reconnecting since
+/// notifications may have been missed in between.
+pub fn listen_for_changes<F>(database_url: String, on_change: F)
+where
+    F: Fn(&str) + Send + 'static,
+{
+    std::thread::spawn(move || {
+        loop {
+            let mut conn = match PgConnection::establish(&database_url)
+                .map_err(|e| e.to_string())
+                .and_then(|mut conn| {
+                    diesel::sql_query(format!("LISTEN {}", SYNC_CHANNEL))
+                        .execute(&mut conn)
+                        .map(|_| conn)
+                        .map_err(|e| e.to_string())
+                }) {
+                Ok(conn) => conn,
+                Err(e) => {
+                    tracing::error!("cache listener failed to connect: {}", e);
+                    std::thread::sleep(Duration::from_secs(10));
+                    continue;
+                }
+            };
+            tracing::info!("cache listener waiting for {} notifications", SYNC_CHANNEL);
+            on_change("");
+
+            'poll: loop {
+                for notification in conn.notifications_iter() {
+                    match notification {
+                        Ok(n) => {
+                            tracing::info!("sync notification ({}), invalidating caches", n.payload);
+                            on_change(&n.payload);
+                        }
+                        Err(e) => {
+                            tracing::error!("cache listener lost connection: {}", e);
+                            break 'poll;
+                        }
+                    }
+                }
+                std::thread::sleep(LISTEN_POLL_INTERVAL);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_evicts_least_recently_used() {
+        let mut lru = Lru::new(2);
+        lru.insert("a", 1);
+        lru.insert("b", 2);
+        assert_eq!(lru.get(&"a"), Some(1));
+        lru.insert("c", 3);
+        assert_eq!(lru.get(&"b"), None);
+        assert_eq!(lru.get(&"a"), Some(1));
+        assert_eq!(lru.get(&"c"), Some(3));
+        assert_eq!(lru.len(), 2);
+    }
+
+    #[test]
+    fn test_lru_reinsert_replaces_value() {
+        let mut lru = Lru::new(2);
+        lru.insert("a", 1);
+        lru.insert("a", 2);
+        assert_eq!(lru.len(), 1);
+        assert_eq!(lru.get(&"a"), Some(2));
+        lru.clear();
+        assert_eq!(lru.get(&"a"), None);
+    }
+
+    fn row(date: NaiveDate) -> SelectPrayerTime {
+        let time = chrono::NaiveTime::from_hms_opt(6, 0, 0).unwrap();
+        SelectPrayerTime {
+            id: 1,
+            zone_code: "SGR01".to_string(),
+            date,
+            imsak: time,
+            fajr: time,
+            syuruk: time,
+            dhuhr: time,
+            asr: time,
+            maghrib: time,
+            isha: time,
+            created_at: date.and_time(time),
+            updated_at: date.and_time(time),
+        }
+    }
+
+    #[test]
+    fn test_load_racing_invalidate_is_not_cached() {
+        let cache = ReadCache::default();
+        let day = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+
+        // A sync notification arrives while the rows are being loaded
+        let rows = cache
+            .prayer_times_with("SGR01", day, day, |_, _| {
+                cache.invalidate();
+                Ok(vec![row(day)])
+            })
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(cache.prayer_time_blocks(), 0);
+
+        let generation = AtomicU64::new(0);
+        let slot = RwLock::default();
+        let stats = CacheStats::default();
+        let list = || {
+            Ok(CachedList {
+                items: vec![1],
+                last_modified: None,
+            })
+        };
+        let loaded = cached_list(&generation, &slot, &stats, || {
+            generation.fetch_add(1, Ordering::SeqCst);
+            list()
+        });
+        assert_eq!(loaded.unwrap().items, vec![1]);
+        assert!(slot.read().unwrap().is_none());
+        cached_list(&generation, &slot, &stats, list).unwrap();
+        assert!(slot.read().unwrap().is_some());
+
+        // Without an invalidation in between, loads are kept
+        cache.prayer_times_with("SGR01", day, day, |_, _| Ok(vec![row(day)])).unwrap();
+        assert_eq!(cache.prayer_time_blocks(), 1);
+    }
+
+    #[test]
+    fn test_month_bounds() {
+        let date = NaiveDate::from_ymd_opt(2028, 2, 17).unwrap();
+        assert_eq!(month_start(date), NaiveDate::from_ymd_opt(2028, 2, 1).unwrap());
+        assert_eq!(month_end(month_start(date)), NaiveDate::from_ymd_opt(2028, 2, 29).unwrap());
+    }
+}
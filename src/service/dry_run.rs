@@ -0,0 +1,404 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, NaiveDate, NaiveTime, Utc};
+use diesel::{PgConnection, QueryResult};
+use serde::Serialize;
+
+use crate::{
+    api::data_repo::{self, DataRepo, PrayerTimeRecord},
+    models::{
+        prayer_times::{SelectPrayerTime, select_prayer_times_for_zone},
+        zones::{UpsertZone, select_zones_by_country},
+    },
+    service::{
+        shutdown::Shutdown,
+        sync::{SyncOptions, add_month, resume_month},
+    },
+};
+
+/// What a sync would change, without writing anything.
+#[derive(Debug, Default, Serialize)]
+pub struct SyncDiff {
+    pub countries: Vec<CountryDiff>,
+    /// Fetch and database errors; the diff leaves out whatever they covered
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CountryDiff {
+    pub country: String,
+    /// Zones in the data repo but not in the database
+    pub new_zones: Vec<String>,
+    /// Zones in the database but no longer in the data repo; sync keeps them
+    pub removed_zones: Vec<String>,
+    /// Changed `state` or `location`
+    pub renamed_zones: Vec<ZoneChange>,
+    pub timezone_changes: Vec<ZoneChange>,
+    /// Zones whose prayer times differ, in zone order
+    pub prayer_times: Vec<PrayerTimesDiff>,
+}
+
+impl CountryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.new_zones.is_empty()
+            && self.removed_zones.is_empty()
+            && self.renamed_zones.is_empty()
+            && self.timezone_changes.is_empty()
+            && self.prayer_times.is_empty()
+    }
+}
+
+/// A zone field whose stored value differs from the data repo.
+#[derive(Debug, Serialize)]
+pub struct ZoneChange {
+    pub zone: String,
+    pub field: &'static str,
+    pub stored: String,
+    pub fetched: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrayerTimesDiff {
+    pub zone: String,
+    /// Days that would be inserted, as runs of consecutive dates
+    pub inserted: Vec<DateRange>,
+    /// Stored times that differ; only overwritten with `--force`
+    pub changed: Vec<ChangedTime>,
+}
+
+impl PrayerTimesDiff {
+    pub fn inserted_days(&self) -> i64 {
+        self.inserted.iter().map(|r| (r.to - r.from).num_days() + 1).sum()
+    }
+}
+
+/// Inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct ChangedTime {
+    pub date: NaiveDate,
+    pub prayer: &'static str,
+    pub stored: NaiveTime,
+    pub fetched: NaiveTime,
+}
+
+/// Runs `f` in a read-only transaction, so a dry run cannot write even by
+/// mistake.
+fn read_only<T>(conn: &mut PgConnection, f: impl FnOnce(&mut PgConnection) -> QueryResult<T>) -> QueryResult<T> {
+    conn.build_transaction().read_only().run(f)
+}
+
+/// Collapses sorted dates into runs of consecutive days.
+fn date_ranges(dates: &[NaiveDate]) -> Vec<DateRange> {
+    let mut ranges: Vec<DateRange> = Vec::new();
+    for &date in dates {
+        match ranges.last_mut() {
+            Some(range) if range.to.succ_opt() == Some(date) => range.to = date,
+            _ => ranges.push(DateRange { from: date, to: date }),
+        }
+    }
+    ranges
+}
+
+/// Times of `fetched` that differ from `stored`, in prayer order.
+fn changed_times(stored: &SelectPrayerTime, fetched: &PrayerTimeRecord) -> Vec<ChangedTime> {
+    [
+        ("imsak", stored.imsak, fetched.imsak),
+        ("fajr", stored.fajr, fetched.fajr),
+        ("syuruk", stored.syuruk, fetched.syuruk),
+        ("dhuhr", stored.dhuhr, fetched.dhuhr),
+        ("asr", stored.asr, fetched.asr),
+        ("maghrib", stored.maghrib, fetched.maghrib),
+        ("isha", stored.isha, fetched.isha),
+    ]
+    .into_iter()
+    .filter(|(_, old, new)| old != new)
+    .map(|(prayer, old, new)| ChangedTime {
+        date: fetched.date,
+        prayer,
+        stored: old,
+        fetched: new,
+    })
+    .collect()
+}
+
+/// Compares the rows a sync would store with those already stored.
+fn diff_prayer_times(zone_code: &str, stored: &[SelectPrayerTime], fetched: &[PrayerTimeRecord]) -> PrayerTimesDiff {
+    let stored: HashMap<NaiveDate, &SelectPrayerTime> = stored.iter().map(|row| (row.date, row)).collect();
+    let mut inserted = Vec::new();
+    let mut changed = Vec::new();
+    for record in fetched {
+        match stored.get(&record.date) {
+            Some(row) => changed.extend(changed_times(row, record)),
+            None => inserted.push(record.date),
+        }
+    }
+    inserted.sort();
+    inserted.dedup();
+
+    PrayerTimesDiff {
+        zone: zone_code.to_string(),
+        inserted: date_ranges(&inserted),
+        changed,
+    }
+}
+
+struct DryRun<'a> {
+    repo: &'a DataRepo,
+    options: &'a SyncOptions,
+    shutdown: &'a Shutdown,
+    today: NaiveDate,
+    errors: Vec<String>,
+}
+
+impl DryRun<'_> {
+    fn error(&mut self, message: String) {
+        tracing::error!("[dry-run] {}", message);
+        self.errors.push(message);
+    }
+
+    /// Fetches the months a sync would walk for `zone`, like
+    /// [`crate::service::sync`]: up to the first empty month or the last one.
+    async fn fetch_months(&mut self, zone: &UpsertZone, start: NaiveDate) -> Vec<PrayerTimeRecord> {
+        let end = self.options.last_month(self.today);
+        let mut records = Vec::new();
+        let mut cursor = start;
+        while cursor <= end && !self.shutdown.is_requested() {
+            match data_repo::fetch_prayer_times(self.repo, &zone.country, &zone.zone_code, cursor.year(), cursor.month())
+                .await
+            {
+                Ok(month) if month.is_empty() => break,
+                Ok(month) => records.extend(month),
+                Err(e) => {
+                    self.error(format!(
+                        "fetch error for {} {}-{:02}: {:?}",
+                        zone.zone_code,
+                        cursor.year(),
+                        cursor.month(),
+                        e
+                    ));
+                    break;
+                }
+            }
+            cursor = add_month(cursor);
+        }
+        records
+    }
+
+    async fn zone_prayer_times(&mut self, conn: &mut PgConnection, zone: &UpsertZone) -> Option<PrayerTimesDiff> {
+        let start = match self.options.from {
+            Some(from) => Ok(from),
+            None => read_only(conn, |conn| resume_month(conn, &zone.zone_code, self.today)),
+        };
+        let start = match start {
+            Ok(start) => start,
+            Err(e) => {
+                self.error(format!("db error for {}: {}", zone.zone_code, e));
+                return None;
+            }
+        };
+
+        let fetched = self.fetch_months(zone, start).await;
+        let last = fetched.iter().map(|r| r.date).max()?;
+        match read_only(conn, |conn| select_prayer_times_for_zone(conn, &zone.zone_code, start, last)) {
+            Ok(stored) => Some(diff_prayer_times(&zone.zone_code, &stored, &fetched)),
+            Err(e) => {
+                self.error(format!("db error reading prayer times for {}: {}", zone.zone_code, e));
+                None
+            }
+        }
+    }
+
+    async fn country(&mut self, conn: &mut PgConnection, country_code: &str) -> Option<CountryDiff> {
+        let repo_zones = match data_repo::fetch_zones(self.repo, country_code).await {
+            Ok(zones) => zones,
+            Err(e) => {
+                self.error(format!("failed to fetch zones for {}: {:?}", country_code, e));
+                return None;
+            }
+        };
+        let stored_zones = match read_only(conn, |conn| select_zones_by_country(conn, country_code)) {
+            Ok(zones) => zones,
+            Err(e) => {
+                self.error(format!("db error reading zones for {}: {}", country_code, e));
+                return None;
+            }
+        };
+
+        let wanted = |code: &str| self.options.zone.as_deref().is_none_or(|zone| zone == code);
+        let fetched: BTreeMap<String, UpsertZone> = repo_zones
+            .iter()
+            .filter(|z| wanted(&z.code))
+            .map(|z| (z.code.clone(), z.into()))
+            .collect();
+        let stored: BTreeMap<String, UpsertZone> = stored_zones
+            .into_iter()
+            .filter(|z| wanted(&z.zone_code))
+            .map(|z| (z.zone_code.clone(), z))
+            .collect();
+        if let Some(ref zone_code) = self.options.zone
+            && fetched.is_empty()
+            && stored.is_empty()
+        {
+            self.error(format!("zone {} not found in {}", zone_code, country_code));
+            return None;
+        }
+
+        let mut diff = CountryDiff {
+            country: country_code.to_string(),
+            new_zones: fetched.keys().filter(|code| !stored.contains_key(*code)).cloned().collect(),
+            removed_zones: stored.keys().filter(|code| !fetched.contains_key(*code)).cloned().collect(),
+            ..Default::default()
+        };
+        for (code, zone) in &fetched {
+            let Some(old) = stored.get(code) else {
+                continue;
+            };
+            let change = |field, stored: &String, fetched: &String| {
+                (stored != fetched).then(|| ZoneChange {
+                    zone: code.clone(),
+                    field,
+                    stored: stored.clone(),
+                    fetched: fetched.clone(),
+                })
+            };
+            diff.renamed_zones.extend(change("state", &old.state, &zone.state));
+            diff.renamed_zones.extend(change("location", &old.location, &zone.location));
+            diff.timezone_changes.extend(change("timezone", &old.timezone, &zone.timezone));
+        }
+
+        tracing::info!("[dry-run] comparing prayer times for {} ({} zones)", country_code, fetched.len());
+        for zone in fetched.values() {
+            if self.shutdown.is_requested() {
+                break;
+            }
+            if let Some(zone_diff) = self.zone_prayer_times(conn, zone).await
+                && !(zone_diff.inserted.is_empty() && zone_diff.changed.is_empty())
+            {
+                diff.prayer_times.push(zone_diff);
+            }
+        }
+        Some(diff)
+    }
+}
+
+/// Fetches what [`crate::service::sync::sync_country`] (or, without a
+/// country, `sync_all`) would with the same `options` and compares it with
+/// the database, reading it only in read-only transactions. Takes no locks,
+/// so a sync running meanwhile can make the diff stale.
+pub async fn dry_run(
+    conn: &mut PgConnection,
+    repo: &DataRepo,
+    country_code: Option<&str>,
+    options: &SyncOptions,
+    shutdown: &Shutdown,
+) -> SyncDiff {
+    let mut run = DryRun {
+        repo,
+        options,
+        shutdown,
+        today: Utc::now().date_naive(),
+        errors: Vec::new(),
+    };
+
+    let country_codes = match country_code {
+        Some(code) => vec![code.to_string()],
+        None => match data_repo::fetch_countries(repo).await {
+            Ok(countries) => countries.into_iter().map(|c| c.code).collect(),
+            Err(e) => {
+                run.error(format!("failed to fetch countries: {:?}", e));
+                Vec::new()
+            }
+        },
+    };
+
+    let mut countries = Vec::new();
+    for code in &country_codes {
+        if shutdown.is_requested() {
+            break;
+        }
+        countries.extend(run.country(conn, code).await);
+    }
+    if shutdown.is_requested() {
+        run.error("interrupted by shutdown, diff is incomplete".to_string());
+    }
+
+    SyncDiff {
+        countries,
+        errors: run.errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 2, day).unwrap()
+    }
+
+    fn time(hour: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    fn record(day: u32, fajr: NaiveTime) -> PrayerTimeRecord {
+        PrayerTimeRecord {
+            date: date(day),
+            imsak: time(5),
+            fajr,
+            syuruk: time(7),
+            dhuhr: time(13),
+            asr: time(16),
+            maghrib: time(19),
+            isha: time(20),
+        }
+    }
+
+    fn row(day: u32, fajr: NaiveTime) -> SelectPrayerTime {
+        let r = record(day, fajr);
+        SelectPrayerTime {
+            id: day.into(),
+            zone_code: "ZZZ01".to_string(),
+            date: r.date,
+            imsak: r.imsak,
+            fajr: r.fajr,
+            syuruk: r.syuruk,
+            dhuhr: r.dhuhr,
+            asr: r.asr,
+            maghrib: r.maghrib,
+            isha: r.isha,
+            created_at: r.date.and_time(time(0)),
+            updated_at: r.date.and_time(time(0)),
+        }
+    }
+
+    #[test]
+    fn test_diff_prayer_times() {
+        let stored = vec![row(1, time(6)), row(2, time(4))];
+        let fetched: Vec<_> = [1, 2, 3, 4, 6].into_iter().map(|day| record(day, time(6))).collect();
+
+        let diff = diff_prayer_times("ZZZ01", &stored, &fetched);
+        assert_eq!(
+            diff.inserted,
+            vec![
+                DateRange { from: date(3), to: date(4) },
+                DateRange { from: date(6), to: date(6) },
+            ]
+        );
+        assert_eq!(diff.inserted_days(), 3);
+        assert_eq!(
+            diff.changed,
+            vec![ChangedTime {
+                date: date(2),
+                prayer: "fajr",
+                stored: time(4),
+                fetched: time(6),
+            }]
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use tokio::{sync::mpsc, task::JoinHandle};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    api::data_repo::DataRepo,
+    models::db::DbPool,
+    service::{
+        shutdown::Shutdown,
+        sync::{SyncOptions, SyncOutcome, SyncProgress, sync_all, sync_country},
+    },
+};
+
+/// Finished jobs kept for status queries; older ones are forgotten.
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// Jobs that may wait for the worker at once.
+pub const MAX_QUEUED_JOBS: usize = 16;
+
+/// Returned by [`SyncJobs::enqueue`] when [`MAX_QUEUED_JOBS`] are waiting.
+#[derive(Debug, thiserror::Error)]
+#[error("{} syncs are already queued", MAX_QUEUED_JOBS)]
+pub struct QueueFull;
+
+/// What a sync job covers.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(tag = "scope", rename_all = "snake_case")]
+pub enum SyncTarget {
+    All,
+    Country { country: String },
+    Zone { country: String, zone: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    /// Finished without errors
+    Succeeded,
+    /// Finished with errors, or interrupted by shutdown
+    Failed,
+}
+
+/// A sync job and its progress so far.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SyncJob {
+    pub id: Uuid,
+    pub target: SyncTarget,
+    pub status: JobStatus,
+    /// Zones synced so far
+    pub zones_done: usize,
+    /// Zones to sync; for all countries, grows as each country starts
+    pub zones_total: usize,
+    pub errors: Vec<String>,
+    /// UTC
+    pub created_at: NaiveDateTime,
+    pub started_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+struct JobEntry {
+    target: SyncTarget,
+    status: JobStatus,
+    progress: Arc<SyncProgress>,
+    created_at: NaiveDateTime,
+    started_at: Option<NaiveDateTime>,
+    finished_at: Option<NaiveDateTime>,
+}
+
+impl JobEntry {
+    fn snapshot(&self, id: Uuid) -> SyncJob {
+        SyncJob {
+            id,
+            target: self.target.clone(),
+            status: self.status,
+            zones_done: self.progress.zones_done(),
+            zones_total: self.progress.zones_total(),
+            errors: self.progress.errors(),
+            created_at: self.created_at,
+            started_at: self.started_at,
+            finished_at: self.finished_at,
+        }
+    }
+}
+
+#[derive(Default)]
+struct JobTable {
+    jobs: HashMap<Uuid, JobEntry>,
+    finished: VecDeque<Uuid>,
+}
+
+/// Sync jobs requested over the admin API, run one at a time by a background
+/// worker. Jobs live in this process's memory only.
+pub struct SyncJobs {
+    table: Mutex<JobTable>,
+    queue: mpsc::Sender<Uuid>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl SyncJobs {
+    /// Starts the worker, which syncs on connections from `pool` and stops
+    /// once shutdown is requested. Jobs still queued then are failed.
+    pub fn spawn(pool: DbPool, repo: DataRepo, shutdown: Shutdown) -> Arc<Self> {
+        let (queue, mut receiver) = mpsc::channel(MAX_QUEUED_JOBS);
+        let jobs = Arc::new(Self {
+            table: Mutex::default(),
+            queue,
+            worker: Mutex::default(),
+        });
+
+        let worker = jobs.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                let id = tokio::select! {
+                    id = receiver.recv() => match id {
+                        Some(id) => id,
+                        None => break,
+                    },
+                    _ = shutdown.requested() => break,
+                };
+                worker.run(id, &pool, &repo, &shutdown).await;
+            }
+            receiver.close();
+            while let Some(id) = receiver.recv().await {
+                worker.finish(id, Some("cancelled by shutdown".to_string()));
+            }
+            tracing::info!("[jobs] worker stopped");
+        });
+        *jobs.worker.lock().unwrap() = Some(handle);
+        jobs
+    }
+
+    /// Resolves once the worker has stopped, after shutdown was requested.
+    pub async fn stopped(&self) {
+        let handle = self.worker.lock().unwrap().take();
+        if let Some(handle) = handle {
+            let _ = handle.await;
+        }
+    }
+
+    /// Queues a sync and returns its job. A target that is already waiting
+    /// is not queued twice; its existing job is returned instead.
+    pub fn enqueue(&self, target: SyncTarget) -> Result<SyncJob, QueueFull> {
+        let mut table = self.table.lock().unwrap();
+        if let Some((&id, entry)) = table
+            .jobs
+            .iter()
+            .find(|(_, entry)| entry.status == JobStatus::Queued && entry.target == target)
+        {
+            return Ok(entry.snapshot(id));
+        }
+
+        let id = Uuid::new_v4();
+        let entry = JobEntry {
+            target,
+            status: JobStatus::Queued,
+            progress: Arc::default(),
+            created_at: Utc::now().naive_utc(),
+            started_at: None,
+            finished_at: None,
+        };
+        let job = entry.snapshot(id);
+        match self.queue.try_send(id) {
+            Ok(()) => {
+                table.jobs.insert(id, entry);
+            }
+            Err(mpsc::error::TrySendError::Full(_)) => return Err(QueueFull),
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                table.jobs.insert(id, entry);
+                drop(table);
+                self.finish(id, Some("sync worker is not running".to_string()));
+                return Ok(self.get(id).unwrap_or(job));
+            }
+        }
+        tracing::info!("[jobs] queued {} ({:?})", id, job.target);
+        Ok(job)
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<SyncJob> {
+        self.table.lock().unwrap().jobs.get(&id).map(|entry| entry.snapshot(id))
+    }
+
+    /// Marks a job as started and returns what it covers.
+    fn start(&self, id: Uuid) -> Option<(SyncTarget, Arc<SyncProgress>)> {
+        let mut table = self.table.lock().unwrap();
+        let entry = table.jobs.get_mut(&id)?;
+        entry.status = JobStatus::Running;
+        entry.started_at = Some(Utc::now().naive_utc());
+        Some((entry.target.clone(), entry.progress.clone()))
+    }
+
+    fn finish(&self, id: Uuid, error: Option<String>) {
+        let mut table = self.table.lock().unwrap();
+        let Some(entry) = table.jobs.get_mut(&id) else {
+            return;
+        };
+        if let Some(error) = error {
+            entry.progress.error(error);
+        }
+        entry.status = if entry.progress.errors().is_empty() {
+            JobStatus::Succeeded
+        } else {
+            JobStatus::Failed
+        };
+        entry.finished_at = Some(Utc::now().naive_utc());
+
+        table.finished.push_back(id);
+        while table.finished.len() > MAX_FINISHED_JOBS {
+            if let Some(old) = table.finished.pop_front() {
+                table.jobs.remove(&old);
+            }
+        }
+    }
+
+    async fn run(&self, id: Uuid, pool: &DbPool, repo: &DataRepo, shutdown: &Shutdown) {
+        let Some((target, progress)) = self.start(id) else {
+            return;
+        };
+        tracing::info!("[jobs] running {} ({:?})", id, target);
+
+        let mut conn = match pool.get() {
+            Ok(conn) => conn,
+            Err(e) => return self.finish(id, Some(format!("no database connection: {}", e))),
+        };
+
+        let skipped = match target {
+            SyncTarget::All => sync_all(&mut conn, repo, &SyncOptions::default(), &progress, shutdown).await,
+            SyncTarget::Country { ref country } | SyncTarget::Zone { ref country, .. } => {
+                let options = SyncOptions {
+                    zone: match target {
+                        SyncTarget::Zone { ref zone, .. } => Some(zone.clone()),
+                        _ => None,
+                    },
+                    ..Default::default()
+                };
+                match sync_country(&mut conn, repo, country, &options, &progress, shutdown).await {
+                    SyncOutcome::Ran => Vec::new(),
+                    SyncOutcome::Skipped => vec![country.clone()],
+                }
+            }
+        };
+        for country in skipped {
+            progress.error(format!("{} is being synced by another process", country));
+        }
+
+        let interrupted = shutdown.is_requested().then(|| "interrupted by shutdown".to_string());
+        self.finish(id, interrupted);
+        tracing::info!("[jobs] finished {}", id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// No worker: jobs stay queued until started by hand, and their ids wait
+    /// in the returned receiver.
+    fn jobs() -> (SyncJobs, mpsc::Receiver<Uuid>) {
+        let (queue, receiver) = mpsc::channel(MAX_QUEUED_JOBS);
+        let jobs = SyncJobs {
+            table: Mutex::default(),
+            queue,
+            worker: Mutex::default(),
+        };
+        (jobs, receiver)
+    }
+
+    fn country(code: &str) -> SyncTarget {
+        SyncTarget::Country {
+            country: code.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_job_lifecycle() {
+        let (jobs, _receiver) = jobs();
+        let job = jobs.enqueue(country("MY")).unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+
+        let (target, progress) = jobs.start(job.id).unwrap();
+        assert_eq!(target, job.target);
+        assert_eq!(jobs.get(job.id).unwrap().status, JobStatus::Running);
+
+        progress.error("fetch error".to_string());
+        jobs.finish(job.id, None);
+        let finished = jobs.get(job.id).unwrap();
+        assert_eq!(finished.status, JobStatus::Failed);
+        assert_eq!(finished.errors, vec!["fetch error"]);
+        assert!(finished.finished_at.is_some());
+
+        let ok = jobs.enqueue(SyncTarget::All).unwrap();
+        jobs.start(ok.id);
+        jobs.finish(ok.id, None);
+        assert_eq!(jobs.get(ok.id).unwrap().status, JobStatus::Succeeded);
+        assert!(jobs.get(Uuid::new_v4()).is_none());
+    }
+
+    #[test]
+    fn test_forgets_oldest_finished_jobs() {
+        let (jobs, mut receiver) = jobs();
+        let ids: Vec<Uuid> = (0..MAX_FINISHED_JOBS + 1)
+            .map(|_| {
+                let id = jobs.enqueue(SyncTarget::All).unwrap().id;
+                receiver.try_recv().unwrap();
+                jobs.finish(id, None);
+                id
+            })
+            .collect();
+        assert!(jobs.get(ids[0]).is_none());
+        assert!(jobs.get(ids[1]).is_some());
+    }
+
+    #[test]
+    fn test_queued_targets_are_merged_and_bounded() {
+        let (jobs, mut receiver) = jobs();
+        let first = jobs.enqueue(country("MY")).unwrap();
+        assert_eq!(jobs.enqueue(country("MY")).unwrap().id, first.id);
+
+        // Once running, the same target can be queued again
+        receiver.try_recv().unwrap();
+        jobs.start(first.id);
+        assert_ne!(jobs.enqueue(country("MY")).unwrap().id, first.id);
+
+        for i in 1..MAX_QUEUED_JOBS {
+            jobs.enqueue(country(&format!("C{}", i))).unwrap();
+        }
+        assert!(jobs.enqueue(SyncTarget::All).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_fails_queued_jobs() {
+        use diesel::r2d2::{ConnectionManager, Pool};
+
+        // Never connects; a job the worker picks up fails quickly
+        let pool = Pool::builder()
+            .connection_timeout(std::time::Duration::from_millis(50))
+            .build_unchecked(ConnectionManager::new("postgres://127.0.0.1:1/none"));
+        let (tx, shutdown) = Shutdown::channel();
+        let jobs = SyncJobs::spawn(pool, DataRepo::new(&Default::default()), shutdown);
+        let queued = [jobs.enqueue(SyncTarget::All).unwrap(), jobs.enqueue(country("MY")).unwrap()];
+
+        tx.send(true).unwrap();
+        jobs.stopped().await;
+        for job in queued {
+            let job = jobs.get(job.id).unwrap();
+            assert_eq!(job.status, JobStatus::Failed);
+            assert!(job.finished_at.is_some());
+        }
+        let late = jobs.enqueue(SyncTarget::All).unwrap();
+        assert_eq!(late.status, JobStatus::Failed);
+    }
+}
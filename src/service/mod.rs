@@ -1 +1,9 @@
+pub mod api_keys;
+pub mod cache;
+pub mod dry_run;
+pub mod jobs;
+pub mod rate_limit;
+pub mod schedule;
+pub mod search;
+pub mod shutdown;
 pub mod sync;
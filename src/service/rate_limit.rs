@@ -0,0 +1,273 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use ipnet::IpNet;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::config::StringList;
+
+/// Default per-IP budget in cost units per minute.
+pub const DEFAULT_UNITS_PER_MINUTE: i32 = 300;
+
+/// How often idle buckets are dropped.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Classic token bucket: holds up to one minute's worth of units and refills
+/// continuously.
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub fn per_minute(units: i32, now: Instant) -> Self {
+        let capacity = units.max(1) as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / 60.0,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Takes `cost` tokens, or returns how long until enough are available.
+    /// Costs above the capacity are charged as a full bucket.
+    pub fn try_take(&mut self, cost: f64, now: Instant) -> Result<(), Duration> {
+        let cost = cost.min(self.capacity);
+        self.refill(now);
+        if self.tokens >= cost {
+            self.tokens -= cost;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((cost - self.tokens) / self.refill_per_sec))
+    }
+
+    pub fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+
+    pub fn capacity(&self) -> f64 {
+        self.capacity
+    }
+
+    pub fn remaining(&self) -> f64 {
+        self.tokens
+    }
+
+    /// Time until the bucket is full again.
+    pub fn reset_after(&self) -> Duration {
+        Duration::from_secs_f64((self.capacity - self.tokens) / self.refill_per_sec)
+    }
+
+    pub fn status(&self) -> RateLimitStatus {
+        RateLimitStatus {
+            limit: self.capacity() as u64,
+            remaining: self.remaining().floor() as u64,
+            reset_after: self.reset_after(),
+        }
+    }
+}
+
+/// Per-IP limits applied to requests without a valid API key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitPolicy {
+    pub units_per_minute: i32,
+    /// Proxies whose `X-Forwarded-For` entries are believed.
+    #[serde(
+        serialize_with = "serialize_trusted_proxies",
+        deserialize_with = "deserialize_trusted_proxies"
+    )]
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> Self {
+        Self {
+            units_per_minute: DEFAULT_UNITS_PER_MINUTE,
+            trusted_proxies: Vec::new(),
+        }
+    }
+}
+
+impl RateLimitPolicy {
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|net| net.contains(&ip))
+    }
+
+    /// The client address: the peer itself, or when the peer is a trusted
+    /// proxy, the right-most `X-Forwarded-For` entry not added by one.
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+        let Some(forwarded_for) = forwarded_for else {
+            return peer;
+        };
+
+        let mut client = peer;
+        for hop in forwarded_for.rsplit(',').map(str::trim) {
+            let Ok(ip) = hop.parse::<IpAddr>() else {
+                break;
+            };
+            client = ip;
+            if !self.is_trusted(ip) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+pub fn parse_trusted_proxies(value: &str) -> Result<Vec<IpNet>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse::<IpNet>()
+                .or_else(|_| s.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| format!("invalid trusted proxy: {}", s))
+        })
+        .collect()
+}
+
+fn serialize_trusted_proxies<S: Serializer>(proxies: &[IpNet], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(proxies.iter().map(ToString::to_string))
+}
+
+fn deserialize_trusted_proxies<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<IpNet>, D::Error> {
+    let proxies = StringList::deserialize(deserializer)?;
+    parse_trusted_proxies(&proxies.joined()).map_err(serde::de::Error::custom)
+}
+
+/// Snapshot of a client's bucket after a request, for `RateLimit-*` headers.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitStatus {
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset_after: Duration,
+}
+
+pub struct IpRateLimiter {
+    policy: RateLimitPolicy,
+    buckets: Mutex<HashMap<IpAddr, TokenBucket>>,
+    last_prune: Mutex<Instant>,
+}
+
+impl IpRateLimiter {
+    pub fn new(policy: RateLimitPolicy) -> Self {
+        Self {
+            policy,
+            buckets: Mutex::new(HashMap::new()),
+            last_prune: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn policy(&self) -> &RateLimitPolicy {
+        &self.policy
+    }
+
+    /// Charges `cost` units to `ip`. On rejection returns how long to wait.
+    pub fn check(&self, ip: IpAddr, cost: f64) -> Result<RateLimitStatus, (RateLimitStatus, Duration)> {
+        let now = Instant::now();
+        self.prune(now);
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(ip)
+            .or_insert_with(|| TokenBucket::per_minute(self.policy.units_per_minute, now));
+        let result = bucket.try_take(cost, now);
+        let status = bucket.status();
+        result.map(|()| status).map_err(|retry_after| (status, retry_after))
+    }
+
+    fn prune(&self, now: Instant) {
+        let mut last_prune = self.last_prune.lock().unwrap();
+        if now.duration_since(*last_prune) < PRUNE_INTERVAL {
+            return;
+        }
+        *last_prune = now;
+        self.buckets.lock().unwrap().retain(|_, b| !b.is_full(now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_limits_and_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::per_minute(60, start);
+        for _ in 0..60 {
+            assert!(bucket.try_take(1.0, start).is_ok());
+        }
+        let retry_after = bucket.try_take(1.0, start).unwrap_err();
+        assert!((retry_after.as_secs_f64() - 1.0).abs() < 1e-6);
+
+        assert!(bucket.try_take(1.0, start + Duration::from_secs(1)).is_ok());
+        assert!(bucket.try_take(1.0, start + Duration::from_secs(1)).is_err());
+        assert!(bucket.is_full(start + Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn test_token_bucket_charges_large_costs_as_full_bucket() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::per_minute(10, start);
+        assert!(bucket.try_take(25.0, start).is_ok());
+        assert_eq!(bucket.remaining(), 0.0);
+        assert_eq!(bucket.reset_after(), Duration::from_secs(60));
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_client_ip_only_trusts_configured_proxies() {
+        let policy = RateLimitPolicy {
+            trusted_proxies: parse_trusted_proxies("10.0.0.0/8, 192.168.1.1").unwrap(),
+            ..Default::default()
+        };
+        // Untrusted peers cannot spoof their address
+        assert_eq!(policy.client_ip(ip("203.0.113.9"), Some("1.2.3.4")), ip("203.0.113.9"));
+        // Trusted proxy chain: skip proxies from the right
+        assert_eq!(
+            policy.client_ip(ip("10.0.0.2"), Some("1.2.3.4, 198.51.100.7, 192.168.1.1")),
+            ip("198.51.100.7")
+        );
+        assert_eq!(policy.client_ip(ip("10.0.0.2"), None), ip("10.0.0.2"));
+        assert_eq!(policy.client_ip(ip("10.0.0.2"), Some("garbage")), ip("10.0.0.2"));
+        assert!(parse_trusted_proxies("10.0.0.0/33").is_err());
+    }
+
+    #[test]
+    fn test_limiter_reports_status() {
+        let limiter = IpRateLimiter::new(RateLimitPolicy {
+            units_per_minute: 10,
+            ..Default::default()
+        });
+        let status = limiter.check(ip("1.1.1.1"), 4.0).unwrap();
+        assert_eq!((status.limit, status.remaining), (10, 6));
+        limiter.check(ip("1.1.1.1"), 6.0).unwrap();
+        let (status, retry_after) = limiter.check(ip("1.1.1.1"), 1.0).unwrap_err();
+        assert_eq!(status.remaining, 0);
+        assert!(retry_after > Duration::ZERO);
+        assert!(limiter.check(ip("2.2.2.2"), 1.0).is_ok());
+    }
+}
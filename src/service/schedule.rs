@@ -0,0 +1,326 @@
+use std::{collections::BTreeMap, str::FromStr, time::Duration};
+
+use chrono::{NaiveDateTime, Utc};
+use croner::Cron;
+use diesel::PgConnection;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use utoipa::ToSchema;
+
+use crate::{
+    api::data_repo::DataRepo,
+    models::{
+        countries::select_countries,
+        db::{AdvisoryLock, DbPool, SYNC_LOCK_KEY},
+        sync_runs::{SyncRun, select_sync_runs},
+    },
+    service::{
+        shutdown::Shutdown,
+        sync::{SyncOptions, SyncProgress, sync_countries, sync_country},
+    },
+};
+
+/// Schedule key applying to every country without its own entry.
+pub const ANY_COUNTRY: &str = "*";
+
+/// Wait before looking again when a due sync could not run (lock held by
+/// another process) or did not complete without errors.
+const RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Cron expression evaluated in UTC, e.g. `0 3 * * 1` (Mondays 03:00).
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    expression: String,
+    cron: Cron,
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expression = s.trim().to_string();
+        let cron = expression
+            .parse()
+            .map_err(|e| format!("invalid cron expression '{}': {}", expression, e))?;
+        Ok(Self { expression, cron })
+    }
+}
+
+impl CronSchedule {
+    pub fn as_str(&self) -> &str {
+        &self.expression
+    }
+
+    /// First scheduled time strictly after `after`.
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        self.cron.find_next_occurrence(&after, false).ok()
+    }
+}
+
+impl Serialize for CronSchedule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.expression)
+    }
+}
+
+impl<'de> Deserialize<'de> for CronSchedule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Sync schedules keyed by country code, or [`ANY_COUNTRY`].
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct SyncSchedules(BTreeMap<String, CronSchedule>);
+
+impl<'de> Deserialize<'de> for SyncSchedules {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let schedules = BTreeMap::<String, CronSchedule>::deserialize(deserializer)?;
+        Ok(Self(schedules.into_iter().map(|(k, v)| (k.to_uppercase(), v)).collect()))
+    }
+}
+
+impl SyncSchedules {
+    /// Parses `CC=expr` entries separated by `;` (cron expressions contain
+    /// commas), e.g. `MY=0 3 * * *;*=0 4 * * 0`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        value
+            .split(';')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|entry| {
+                let (country, expression) = entry
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected CC=expression, got '{}'", entry))?;
+                Ok((country.trim().to_uppercase(), expression.parse()?))
+            })
+            .collect::<Result<_, String>>()
+            .map(Self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn covers_all(&self) -> bool {
+        self.0.contains_key(ANY_COUNTRY)
+    }
+
+    pub fn for_country(&self, country: &str) -> Option<&CronSchedule> {
+        self.0.get(country).or_else(|| self.0.get(ANY_COUNTRY))
+    }
+}
+
+/// A country's schedule and when it next syncs.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ScheduledSync {
+    pub country: String,
+    /// Cron expression, in UTC
+    pub schedule: String,
+    /// Start of the last completed sync (UTC)
+    pub last_run_at: Option<NaiveDateTime>,
+    /// Start of the next sync (UTC); at or before now when one is due
+    pub next_run_at: NaiveDateTime,
+}
+
+/// Next run for every scheduled country: the first schedule time after its
+/// last completed run. Countries never synced are due at `now`, so a
+/// restarted scheduler only runs what it actually missed.
+pub fn plan(
+    schedules: &SyncSchedules,
+    known_countries: &[String],
+    runs: &[SyncRun],
+    now: NaiveDateTime,
+) -> Vec<ScheduledSync> {
+    let mut countries: Vec<&str> = schedules
+        .0
+        .keys()
+        .map(String::as_str)
+        .filter(|c| *c != ANY_COUNTRY)
+        .collect();
+    if schedules.covers_all() {
+        countries.extend(known_countries.iter().map(String::as_str));
+    }
+    countries.sort_unstable();
+    countries.dedup();
+
+    countries
+        .into_iter()
+        .filter_map(|country| {
+            let schedule = schedules.for_country(country)?;
+            let last_run_at = runs.iter().find(|r| r.country == country).map(|r| r.started_at);
+            let next_run_at = match last_run_at {
+                Some(last) => schedule.next_after(last)?,
+                None => now,
+            };
+            Some(ScheduledSync {
+                country: country.to_string(),
+                schedule: schedule.as_str().to_string(),
+                last_run_at,
+                next_run_at,
+            })
+        })
+        .collect()
+}
+
+/// [`plan`] for the countries and runs currently in the database.
+pub fn load_plan(
+    conn: &mut PgConnection,
+    schedules: &SyncSchedules,
+    now: NaiveDateTime,
+) -> Result<Vec<ScheduledSync>, diesel::result::Error> {
+    let known: Vec<String> = select_countries(conn)?.into_iter().map(|c| c.code).collect();
+    let runs = select_sync_runs(conn)?;
+    Ok(plan(schedules, &known, &runs, now))
+}
+
+/// Syncs every country that is due and returns when the next one is.
+async fn run_due(
+    pool: &DbPool,
+    repo: &DataRepo,
+    schedules: &SyncSchedules,
+    shutdown: &Shutdown,
+) -> Result<Option<NaiveDateTime>, String> {
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let Some(mut conn) = AdvisoryLock::try_acquire(conn, SYNC_LOCK_KEY).map_err(|e| e.to_string())? else {
+        tracing::info!("[sync] another process is syncing, retrying in {}s", RETRY_INTERVAL.as_secs());
+        return Ok(None);
+    };
+
+    let now = Utc::now().naive_utc();
+    let mut plan = load_plan(&mut conn, schedules, now).map_err(|e| e.to_string())?;
+
+    // Refresh the country list before syncing, so `*` picks up countries
+    // added to the data repo (or, on a fresh database, finds any at all)
+    if schedules.covers_all() && (plan.is_empty() || plan.iter().any(|s| s.next_run_at <= now)) {
+        if let Err(e) = sync_countries(&mut conn, repo, &SyncProgress::default()).await {
+            tracing::error!("[sync] failed to refresh countries: {:?}", e);
+        }
+        plan = load_plan(&mut conn, schedules, now).map_err(|e| e.to_string())?;
+    }
+
+    for scheduled in plan.iter().filter(|s| s.next_run_at <= now) {
+        if shutdown.is_requested() {
+            break;
+        }
+        tracing::info!("[sync] {} is due (schedule '{}')", scheduled.country, scheduled.schedule);
+        sync_country(&mut conn, repo, &scheduled.country, &SyncOptions::default(), &SyncProgress::default(), shutdown).await;
+    }
+
+    let next = load_plan(&mut conn, schedules, Utc::now().naive_utc())
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|s| s.next_run_at)
+        .min();
+    Ok(next)
+}
+
+/// Runs each country's sync at its scheduled times in the background, on
+/// connections from `pool`. Shares [`SYNC_LOCK_KEY`] with other sync runners.
+pub fn spawn_scheduled_sync(
+    pool: DbPool,
+    repo: DataRepo,
+    schedules: SyncSchedules,
+    shutdown: Shutdown,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        tracing::info!("[sync] following schedules for {} entries", schedules.0.len());
+        while !shutdown.is_requested() {
+            let next = match run_due(&pool, &repo, &schedules, &shutdown).await {
+                Ok(next) => next,
+                Err(e) => {
+                    tracing::error!("[sync] scheduled sync failed: {}", e);
+                    None
+                }
+            };
+
+            // A next run that is not in the future means the due sync did not complete
+            let wait = next
+                .and_then(|next| (next - Utc::now().naive_utc()).to_std().ok())
+                .filter(|wait| !wait.is_zero())
+                .unwrap_or(RETRY_INTERVAL);
+            if let Some(next) = next {
+                tracing::info!("[sync] next scheduled sync at {} UTC", next.format("%Y-%m-%d %H:%M"));
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = shutdown.requested() => {}
+            }
+        }
+        tracing::info!("[sync] scheduler stopped");
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(y: i32, m: u32, d: u32, h: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(h, 0, 0).unwrap()
+    }
+
+    fn run(country: &str, started_at: NaiveDateTime) -> SyncRun {
+        SyncRun {
+            country: country.to_string(),
+            started_at,
+            finished_at: started_at,
+        }
+    }
+
+    #[test]
+    fn test_parse_schedules() {
+        let schedules = SyncSchedules::parse("sg=0 4 1 1 *; *=0 3 * * 1,4").unwrap();
+        assert_eq!(schedules.for_country("SG").unwrap().as_str(), "0 4 1 1 *");
+        assert_eq!(schedules.for_country("MY").unwrap().as_str(), "0 3 * * 1,4");
+        assert!(SyncSchedules::parse("MY").is_err());
+        assert!(SyncSchedules::parse("MY=every day").is_err());
+        assert!(SyncSchedules::parse("MY=0 3 * *").is_err());
+    }
+
+    #[test]
+    fn test_plan_resumes_from_last_run() {
+        let schedules = SyncSchedules::parse("SG=0 4 1 1 *;*=0 3 * * *").unwrap();
+        let known = ["ID".to_string(), "MY".to_string(), "SG".to_string()];
+        let now = at(2026, 10, 19, 10);
+        let runs = [run("MY", at(2026, 10, 19, 3)), run("SG", at(2026, 1, 1, 4)), run("ID", at(2026, 10, 17, 3))];
+
+        let plan = plan(&schedules, &known, &runs, now);
+        let next: Vec<_> = plan.iter().map(|s| (s.country.as_str(), s.next_run_at)).collect();
+        assert_eq!(
+            next,
+            vec![
+                // Missed while stopped: due once, not once per missed slot
+                ("ID", at(2026, 10, 18, 3)),
+                // Ran this morning: not re-run on restart
+                ("MY", at(2026, 10, 20, 3)),
+                ("SG", at(2027, 1, 1, 4)),
+            ]
+        );
+
+        // Countries never synced are due immediately
+        let plan = super::plan(&schedules, &known, &[], now);
+        assert!(plan.iter().all(|s| s.next_run_at == now && s.last_run_at.is_none()));
+
+        // Without `*`, only listed countries are scheduled
+        let only_sg = SyncSchedules::parse("SG=0 4 1 1 *").unwrap();
+        assert_eq!(super::plan(&only_sg, &known, &runs, now).len(), 1);
+    }
+
+    #[test]
+    fn test_failed_sync_stays_due() {
+        // A sync that hit errors records no run, so the country stays due
+        // and is retried instead of waiting a year for its next slot
+        let schedules = SyncSchedules::parse("SG=0 4 1 1 *").unwrap();
+        let runs = [run("SG", at(2026, 1, 1, 4))];
+        let after_failure = at(2027, 1, 1, 5);
+
+        let plan = plan(&schedules, &[], &runs, after_failure);
+        assert_eq!(plan[0].last_run_at, Some(at(2026, 1, 1, 4)));
+        assert_eq!(plan[0].next_run_at, at(2027, 1, 1, 4));
+        assert!(plan[0].next_run_at <= after_failure);
+    }
+}
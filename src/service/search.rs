@@ -0,0 +1,431 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::models::zones::UpsertZone;
+
+/// How long a built index is served before it is rebuilt from the database.
+const INDEX_TTL: Duration = Duration::from_secs(300);
+
+/// Minimum trigram similarity for a word to count as a (typo) match.
+const TRIGRAM_THRESHOLD: f64 = 0.3;
+
+/// Zone fields that are searched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Zone,
+    State,
+    Location,
+}
+
+impl Field {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Field::Zone => "zone",
+            Field::State => "state",
+            Field::Location => "location",
+        }
+    }
+
+    /// State names are shared by many zones, so they rank lower.
+    fn weight(&self) -> f64 {
+        match self {
+            Field::Zone | Field::Location => 1.0,
+            Field::State => 0.8,
+        }
+    }
+}
+
+/// Span of a matched word in the original (un-normalised) field value,
+/// in characters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub field: Field,
+    pub start: usize,
+    pub end: usize,
+}
+
+pub struct SearchHit<'a> {
+    pub zone: &'a UpsertZone,
+    pub score: f64,
+    pub highlights: Vec<Highlight>,
+}
+
+struct Word {
+    field: Field,
+    text: String,
+    start: usize,
+    end: usize,
+    trigrams: HashSet<String>,
+}
+
+struct Entry {
+    zone: UpsertZone,
+    words: Vec<Word>,
+}
+
+/// Folds common Latin diacritics to their ASCII base letter.
+fn fold_char(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' => 'a',
+        'ç' | 'č' => 'c',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' => 'e',
+        'ì' | 'í' | 'î' | 'ï' | 'ī' => 'i',
+        'ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ō' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' => 'u',
+        'ý' | 'ÿ' => 'y',
+        _ => c,
+    }
+}
+
+/// Lower-cases, strips diacritics and splits on non-alphanumeric characters.
+/// Returns each word with its character span in the original string.
+fn tokenize(s: &str) -> Vec<(String, usize, usize)> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+    for (i, c) in s.chars().enumerate() {
+        if c.is_alphanumeric() {
+            if current.is_empty() {
+                start = i;
+            }
+            current.extend(c.to_lowercase().map(fold_char));
+        } else if !current.is_empty() {
+            words.push((std::mem::take(&mut current), start, i));
+        }
+    }
+    if !current.is_empty() {
+        words.push((current, start, s.chars().count()));
+    }
+    words
+}
+
+/// pg_trgm-style trigrams: the word padded with two leading and one trailing space.
+fn trigrams(word: &str) -> HashSet<String> {
+    let padded: Vec<char> = format!("  {} ", word).chars().collect();
+    padded.windows(3).map(|w| w.iter().collect()).collect()
+}
+
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let shared = a.intersection(b).count();
+    let total = a.len() + b.len() - shared;
+    if total == 0 { 0.0 } else { shared as f64 / total as f64 }
+}
+
+/// Optimal string alignment distance (Levenshtein plus transpositions).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Typos allowed for a query token: none below 3 characters, two from 7.
+fn max_typos(token: &str) -> usize {
+    match token.chars().count() {
+        0..=2 => 0,
+        3..=6 => 1,
+        _ => 2,
+    }
+}
+
+/// Scores how well a query token matches an indexed word (0 means no match).
+fn match_score(token: &str, token_trigrams: &HashSet<String>, word: &Word) -> f64 {
+    if word.text == token {
+        return 1.0;
+    } else if word.text.starts_with(token) {
+        return 0.9;
+    } else if token.len() >= 3 && word.text.contains(token) {
+        return 0.7;
+    }
+
+    let sim = similarity(token_trigrams, &word.trigrams);
+    let trigram_score = if sim >= TRIGRAM_THRESHOLD { sim * 0.8 } else { 0.0 };
+    let typos = edit_distance(token, &word.text);
+    let typo_score = if typos <= max_typos(token) {
+        0.8 - 0.15 * typos as f64
+    } else {
+        0.0
+    };
+    trigram_score.max(typo_score)
+}
+
+/// Case/diacritic-insensitive, typo-tolerant index over zone codes, states
+/// and locations.
+pub struct ZoneIndex {
+    entries: Vec<Entry>,
+}
+
+impl ZoneIndex {
+    pub fn new(zones: Vec<UpsertZone>) -> Self {
+        let entries = zones
+            .into_iter()
+            .map(|zone| {
+                let fields = [
+                    (Field::Zone, zone.zone_code.as_str()),
+                    (Field::State, zone.state.as_str()),
+                    (Field::Location, zone.location.as_str()),
+                ];
+                let words = fields
+                    .iter()
+                    .flat_map(|(field, value)| {
+                        tokenize(value).into_iter().map(|(text, start, end)| Word {
+                            field: *field,
+                            trigrams: trigrams(&text),
+                            text,
+                            start,
+                            end,
+                        })
+                    })
+                    .collect();
+                Entry { zone, words }
+            })
+            .collect();
+        Self { entries }
+    }
+
+    /// Returns zones matching every word of the query, best first.
+    pub fn search(&self, query: &str, country: Option<&str>, limit: usize) -> Vec<SearchHit<'_>> {
+        let tokens: Vec<(String, HashSet<String>)> = tokenize(query)
+            .into_iter()
+            .map(|(text, _, _)| {
+                let t = trigrams(&text);
+                (text, t)
+            })
+            .collect();
+        if tokens.is_empty() {
+            return Vec::new();
+        }
+
+        let mut hits: Vec<SearchHit> = self
+            .entries
+            .iter()
+            .filter(|e| country.is_none_or(|c| e.zone.country.eq_ignore_ascii_case(c)))
+            .filter_map(|entry| {
+                let mut total = 0.0;
+                let mut highlights: Vec<Highlight> = Vec::new();
+                for (token, token_trigrams) in &tokens {
+                    let (score, word) = entry
+                        .words
+                        .iter()
+                        .map(|w| (match_score(token, token_trigrams, w) * w.field.weight(), w))
+                        .max_by(|a, b| a.0.total_cmp(&b.0))?;
+                    if score <= 0.0 {
+                        return None;
+                    }
+                    total += score;
+                    let highlight = Highlight {
+                        field: word.field,
+                        start: word.start,
+                        end: word.end,
+                    };
+                    if !highlights.contains(&highlight) {
+                        highlights.push(highlight);
+                    }
+                }
+                Some(SearchHit {
+                    zone: &entry.zone,
+                    score: total / tokens.len() as f64,
+                    highlights,
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.zone.zone_code.cmp(&b.zone.zone_code))
+        });
+        hits.truncate(limit);
+        hits
+    }
+}
+
+/// Shared zone index, lazily (re)built once stale or after an explicit
+/// `invalidate` (e.g. following a sync).
+#[derive(Default)]
+pub struct ZoneSearch {
+    index: RwLock<Option<(Instant, Arc<ZoneIndex>)>>,
+    /// Held while rebuilding, so concurrent searches wait for a single rebuild
+    rebuilding: tokio::sync::Mutex<()>,
+    /// Bumped by `invalidate`, so an index built from zones loaded before it
+    /// is not kept
+    generation: AtomicU64,
+}
+
+impl ZoneSearch {
+    /// Returns the current index unless it is missing or stale.
+    fn cached(&self) -> Option<Arc<ZoneIndex>> {
+        match *self.index.read().unwrap() {
+            Some((built_at, ref index)) if built_at.elapsed() < INDEX_TTL => Some(index.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the current index, or rebuilds it from the zones `load`
+    /// returns. Only one caller loads at a time; the others wait and reuse
+    /// its index.
+    pub async fn index<E>(&self, load: impl FnOnce() -> Result<Vec<UpsertZone>, E>) -> Result<Arc<ZoneIndex>, E> {
+        if let Some(index) = self.cached() {
+            return Ok(index);
+        }
+        let _rebuilding = self.rebuilding.lock().await;
+        if let Some(index) = self.cached() {
+            return Ok(index);
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let index = Arc::new(ZoneIndex::new(load()?));
+        tracing::debug!("rebuilt zone search index ({} zones)", index.entries.len());
+        let mut current = self.index.write().unwrap();
+        if self.generation.load(Ordering::SeqCst) == generation {
+            *current = Some((Instant::now(), index.clone()));
+        }
+        Ok(index)
+    }
+
+    /// Drops the current index so the next search rebuilds it.
+    pub fn invalidate(&self) {
+        let mut current = self.index.write().unwrap();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *current = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(code: &str, country: &str, state: &str, location: &str) -> UpsertZone {
+        UpsertZone {
+            zone_code: code.to_string(),
+            country: country.to_string(),
+            state: state.to_string(),
+            location: location.to_string(),
+            timezone: "Asia/Kuala_Lumpur".to_string(),
+            latitude: None,
+            longitude: None,
+            min_latitude: None,
+            min_longitude: None,
+            max_latitude: None,
+            max_longitude: None,
+        }
+    }
+
+    fn index() -> ZoneIndex {
+        ZoneIndex::new(vec![
+            zone("SGR01", "MY", "Selangor", "Gombak, Petaling, Sepang, Hulu Langat, Hulu Selangor, Shah Alam"),
+            zone("SGR02", "MY", "Selangor", "Kuala Selangor, Sabak Bernam"),
+            zone("WLY01", "MY", "Wilayah Persekutuan", "Kuala Lumpur, Putrajaya"),
+            zone("SGP01", "SG", "Singapore", "Seluruh Singapura"),
+            zone("JTM01", "ID", "Jawa Timur", "Kota Surabaya"),
+        ])
+    }
+
+    #[test]
+    fn test_tokenize_folds_case_and_diacritics() {
+        let words = tokenize("Bañda Acéh, KOTA");
+        assert_eq!(
+            words,
+            vec![
+                ("banda".to_string(), 0, 5),
+                ("aceh".to_string(), 6, 10),
+                ("kota".to_string(), 12, 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("alam", "alam"), 0);
+        assert_eq!(edit_distance("alm", "alam"), 1);
+        assert_eq!(edit_distance("ptealing", "petaling"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_search_multi_word_with_highlights() {
+        let index = index();
+        let hits = index.search("shah alam", None, 10);
+        assert_eq!(hits[0].zone.zone_code, "SGR01");
+        assert_eq!(hits[0].score, 1.0);
+        let location = &hits[0].zone.location;
+        let first = &hits[0].highlights[0];
+        assert_eq!(first.field, Field::Location);
+        let text: String = location.chars().skip(first.start).take(first.end - first.start).collect();
+        assert_eq!(text, "Shah");
+    }
+
+    #[test]
+    fn test_search_tolerates_typos_and_codes() {
+        let index = index();
+        assert_eq!(index.search("petalig", None, 10)[0].zone.zone_code, "SGR01");
+        assert_eq!(index.search("surbaya", None, 10)[0].zone.zone_code, "JTM01");
+        assert_eq!(index.search("shah alm", None, 10)[0].zone.zone_code, "SGR01");
+        assert_eq!(index.search("wly01", None, 10)[0].zone.zone_code, "WLY01");
+        assert!(index.search("xyzzy", None, 10).is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_searches_rebuild_once() {
+        let search = Arc::new(ZoneSearch::default());
+        let loads = Arc::new(AtomicU64::new(0));
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let (search, loads) = (search.clone(), loads.clone());
+                tokio::spawn(async move {
+                    search
+                        .index(|| {
+                            loads.fetch_add(1, Ordering::SeqCst);
+                            std::thread::sleep(Duration::from_millis(50));
+                            Ok::<_, ()>(vec![zone("SGP01", "SG", "Singapore", "Seluruh Singapura")])
+                        })
+                        .await
+                        .unwrap()
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().entries.len(), 1);
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        search.invalidate();
+        assert!(search.cached().is_none());
+        search.index(|| Ok::<_, ()>(Vec::new())).await.unwrap();
+        assert_eq!(search.cached().unwrap().entries.len(), 0);
+    }
+
+    #[test]
+    fn test_search_ranks_location_over_state_and_filters_country() {
+        let index = index();
+        let hits = index.search("selangor", None, 10);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(index.search("wilayah", None, 10)[0].score, 0.8);
+
+        let hits = index.search("sing", Some("sg"), 10);
+        assert_eq!(hits.len(), 1);
+        assert!(index.search("selangor", Some("SG"), 10).is_empty());
+    }
+}
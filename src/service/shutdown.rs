@@ -0,0 +1,82 @@
+use tokio::sync::watch;
+
+/// Handle that reports when the process has been asked to stop (SIGINT or
+/// SIGTERM). Long-running work checks it between units it can finish
+/// cleanly.
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    /// Installs the signal handlers. Must be called from within the runtime.
+    pub fn listen() -> Self {
+        let (tx, shutdown) = Self::channel();
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            tracing::info!("shutdown requested, finishing in-flight work");
+            let _ = tx.send(true);
+        });
+        shutdown
+    }
+
+    pub(crate) fn channel() -> (watch::Sender<bool>, Self) {
+        let (tx, rx) = watch::channel(false);
+        (tx, Self(rx))
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once shutdown has been requested.
+    pub async fn requested(&self) {
+        let mut rx = self.0.clone();
+        if rx.wait_for(|requested| *requested).await.is_err() {
+            // Signal task gone without a request: never resolve
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+async fn wait_for_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requested_resolves_for_every_clone() {
+        let (tx, shutdown) = Shutdown::channel();
+        let other = shutdown.clone();
+        assert!(!shutdown.is_requested());
+        assert!(tokio::time::timeout(Duration::from_millis(10), other.requested()).await.is_err());
+
+        tx.send(true).unwrap();
+        drop(tx);
+        assert!(shutdown.is_requested());
+        other.requested().await;
+        shutdown.requested().await;
+    }
+}
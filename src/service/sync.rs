@@ -1,31 +1,124 @@
+use std::{
+    collections::HashMap,
+    ops::RangeInclusive,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
 use chrono::{Datelike, Months, NaiveDate, Utc};
 use diesel::PgConnection;
 
 use crate::{
-    api::data_repo,
+    api::data_repo::{self, DataRepo},
+    geodesy::{self, Polygon},
     models::{
         countries,
-        prayer_times::{self, select_last_prayer_time_for_zone, upsert_prayer_times},
-        zones::{self, UpsertZone},
+        db::{self, AdvisoryLock, DbPool, SYNC_LOCK_KEY},
+        prayer_times::{self, overwrite_prayer_times, select_last_prayer_time_for_zone, upsert_prayer_times},
+        sync_runs::{SyncRun, upsert_sync_run},
+        zones::{self, UpdateZoneGeometry, UpsertZone},
     },
+    service::shutdown::Shutdown,
 };
 
-fn add_month(date: NaiveDate) -> NaiveDate {
+/// Time between attempts while waiting for another process's country lock.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Whether [`sync_country`] went ahead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncOutcome {
+    /// Synced, possibly cut short by errors or shutdown
+    Ran,
+    /// Not synced: another process holds the country's lock, or it could
+    /// not be taken
+    Skipped,
+}
+
+/// What to sync and how. The default syncs every zone's missing months and
+/// skips countries another process is syncing.
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+    /// Wait for another process's lock on a country instead of skipping it
+    pub wait_for_lock: bool,
+    /// Only sync this zone of the country
+    pub zone: Option<String>,
+    /// First month to fetch (the first of it), instead of each zone's first
+    /// missing month
+    pub from: Option<NaiveDate>,
+    /// Last month to fetch (the first of it), instead of December next year
+    pub to: Option<NaiveDate>,
+    /// Overwrite stored days that differ from the data repo
+    pub force: bool,
+}
+
+impl SyncOptions {
+    /// Whether only part of the data is synced (a zone or a month range).
+    pub fn is_targeted(&self) -> bool {
+        self.zone.is_some() || self.from.is_some() || self.to.is_some()
+    }
+
+    /// Last month to fetch (the first of it): [`SyncOptions::to`], or
+    /// December next year.
+    pub fn last_month(&self, today: NaiveDate) -> NaiveDate {
+        self.to.unwrap_or_else(|| {
+            NaiveDate::from_ymd_opt(today.year() + 1, 12, 1).expect("invalid year for end month")
+        })
+    }
+}
+
+/// Progress of a sync, readable while it runs. Zones are counted as each
+/// country's zone list is fetched, so the total grows during [`sync_all`].
+#[derive(Debug, Default)]
+pub struct SyncProgress {
+    zones_total: AtomicUsize,
+    zones_done: AtomicUsize,
+    errors: Mutex<Vec<String>>,
+}
+
+impl SyncProgress {
+    pub fn zones_total(&self) -> usize {
+        self.zones_total.load(Ordering::Relaxed)
+    }
+
+    pub fn zones_done(&self) -> usize {
+        self.zones_done.load(Ordering::Relaxed)
+    }
+
+    pub fn errors(&self) -> Vec<String> {
+        self.errors.lock().unwrap().clone()
+    }
+
+    fn error_count(&self) -> usize {
+        self.errors.lock().unwrap().len()
+    }
+
+    /// Logs the error and keeps it for whoever is watching.
+    pub fn error(&self, message: String) {
+        tracing::error!("[sync] {}", message);
+        self.errors.lock().unwrap().push(message);
+    }
+}
+
+pub(crate) fn add_month(date: NaiveDate) -> NaiveDate {
     date.checked_add_months(Months::new(1)).expect("date overflow adding 1 month")
 }
 
 /// Sync zones from the data repo for a specific country.
 async fn sync_zones(
-    client: &reqwest::Client,
+    repo: &DataRepo,
     conn: &mut PgConnection,
     country_code: &str,
+    progress: &SyncProgress,
 ) -> Result<Vec<UpsertZone>, Box<dyn std::error::Error>> {
-    let repo_zones = data_repo::fetch_zones(client, country_code).await?;
+    let repo_zones = data_repo::fetch_zones(repo, country_code).await?;
     let mut db_zones = Vec::new();
     for z in &repo_zones {
         let upsert: UpsertZone = z.into();
         if let Err(e) = zones::upsert_zone(conn, upsert) {
-            tracing::error!("[sync] db error upserting zone {}: {}", z.code, e);
+            progress.error(format!("db error upserting zone {}: {}", z.code, e));
             continue;
         }
         db_zones.push(z.into());
@@ -34,41 +127,95 @@ async fn sync_zones(
     Ok(db_zones)
 }
 
-/// Sync prayer times for a single zone from the data repo.
-/// Sequential month-by-month fetch. Stops on first empty month (no more data available).
-async fn sync_zone_prayer_times(
-    client: &reqwest::Client,
+/// Compute zone centroids and bounding boxes from the country GeoJSON.
+/// Features are assigned to zones via the country's mapping on `shape_property`.
+async fn sync_zone_geometry(
+    repo: &DataRepo,
     conn: &mut PgConnection,
     country_code: &str,
-    zone: &UpsertZone,
-    end: NaiveDate,
-) {
-    let last = match select_last_prayer_time_for_zone(conn, &zone.zone_code) {
-        Ok(v) => v,
-        Err(e) => {
-            tracing::error!("[sync] db error for {}: {}", zone.zone_code, e);
-            return;
-        }
+    zones: &[UpsertZone],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(country) = countries::select_country_by_code(conn, country_code)? else {
+        tracing::warn!("[sync] country {} not in db, skipping zone geometry", country_code);
+        return Ok(());
     };
 
-    let now = Utc::now().date_naive();
-    let mut cursor = match last {
-        Some(ref last) => {
-            let next = last.date + chrono::Duration::days(1);
-            NaiveDate::from_ymd_opt(next.year(), next.month(), 1)
-                .expect("invalid date for sync cursor")
+    let geojson = data_repo::fetch_geojson(repo, &country.geojson).await?;
+    let mapping = data_repo::fetch_mapping(repo, &country.mapping).await?;
+
+    let mut polygons: HashMap<&str, Vec<Polygon>> = HashMap::new();
+    for feature in &geojson.features {
+        let Some(shape) = feature.property(&country.shape_property) else {
+            continue;
+        };
+        if let Some(zone_code) = mapping.get(&shape) {
+            polygons.entry(zone_code).or_default().extend(feature.polygons());
         }
-        None => NaiveDate::from_ymd_opt(now.year(), 1, 1)
-            .expect("invalid current year start"),
+    }
+
+    let mut updated = 0;
+    for zone in zones {
+        let Some(zone_polygons) = polygons.get(zone.zone_code.as_str()) else {
+            tracing::warn!("[sync] no geometry mapped for zone {}", zone.zone_code);
+            continue;
+        };
+        let (Some((latitude, longitude)), Some(bbox)) = (
+            geodesy::centroid(zone_polygons),
+            geodesy::bounding_box(zone_polygons),
+        ) else {
+            continue;
+        };
+
+        let geometry = UpdateZoneGeometry {
+            latitude,
+            longitude,
+            min_latitude: bbox.min_lat,
+            min_longitude: bbox.min_lng,
+            max_latitude: bbox.max_lat,
+            max_longitude: bbox.max_lng,
+        };
+        if let Err(e) = zones::update_zone_geometry(conn, &zone.zone_code, &geometry) {
+            tracing::error!("[sync] db error updating geometry for {}: {}", zone.zone_code, e);
+            continue;
+        }
+        updated += 1;
+    }
+    tracing::info!("[sync] updated geometry for {} zones in {}", updated, country_code);
+    Ok(())
+}
+
+/// First month a zone is missing: the month of the day after its last stored
+/// one, or January this year when it has none.
+pub(crate) fn resume_month(conn: &mut PgConnection, zone_code: &str, today: NaiveDate) -> Result<NaiveDate, diesel::result::Error> {
+    let next = match select_last_prayer_time_for_zone(conn, zone_code)? {
+        Some(last) => last.date + chrono::Duration::days(1),
+        None => NaiveDate::from_ymd_opt(today.year(), 1, 1).expect("invalid current year start"),
     };
+    Ok(NaiveDate::from_ymd_opt(next.year(), next.month(), 1).expect("invalid date for sync cursor"))
+}
 
-    while cursor <= end {
+/// Sync prayer times for a single zone from the data repo, for `months`
+/// (each given as its first day).
+/// Sequential month-by-month fetch. Stops on first empty month (no more data available),
+/// or before the next month once shutdown is requested. Days already stored
+/// are kept, unless `force` overwrites those that changed.
+async fn sync_zone_prayer_times(
+    repo: &DataRepo,
+    conn: &mut PgConnection,
+    zone: &UpsertZone,
+    months: RangeInclusive<NaiveDate>,
+    force: bool,
+    progress: &SyncProgress,
+    shutdown: &Shutdown,
+) {
+    let mut cursor = *months.start();
+    while cursor <= *months.end() && !shutdown.is_requested() {
         let year = cursor.year();
         let month = cursor.month();
 
         let records = match data_repo::fetch_prayer_times(
-            client,
-            country_code,
+            repo,
+            &zone.country,
             &zone.zone_code,
             year,
             month,
@@ -77,10 +224,10 @@ async fn sync_zone_prayer_times(
         {
             Ok(r) => r,
             Err(e) => {
-                tracing::error!(
-                    "[sync] fetch error for {} {}-{:02}: {:?}",
+                progress.error(format!(
+                    "fetch error for {} {}-{:02}: {:?}",
                     zone.zone_code, year, month, e
-                );
+                ));
                 break;
             }
         };
@@ -92,25 +239,24 @@ async fn sync_zone_prayer_times(
 
         let prayer_times: Vec<prayer_times::UpsertPrayerTime> = records
             .iter()
-            .filter(|r| {
-                if let Some(ref last) = last {
-                    r.date > last.date
-                } else {
-                    true
-                }
-            })
             .map(|r| prayer_times::to_upsert(&zone.zone_code, r))
             .collect();
 
-        if !prayer_times.is_empty() {
-            tracing::info!(
-                "[sync] upserting {} records for {}",
-                prayer_times.len(),
-                zone.zone_code
-            );
-            if let Err(e) = upsert_prayer_times(conn, &prayer_times) {
-                tracing::error!("[sync] db error upserting for {}: {}", zone.zone_code, e);
-            }
+        let stored = if force {
+            overwrite_prayer_times(conn, &prayer_times)
+        } else {
+            upsert_prayer_times(conn, &prayer_times)
+        };
+        match stored {
+            Ok(0) => {}
+            Ok(count) => tracing::info!(
+                "[sync] stored {} records for {} {}-{:02}",
+                count,
+                zone.zone_code,
+                year,
+                month
+            ),
+            Err(e) => progress.error(format!("db error upserting for {}: {}", zone.zone_code, e)),
         }
 
         cursor = add_month(cursor);
@@ -118,61 +264,205 @@ async fn sync_zone_prayer_times(
 }
 
 /// Sync all prayer times for a country from the data repo.
-pub async fn sync_country(conn: &mut PgConnection, country_code: &str) {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .expect("failed to build HTTP client");
+/// Months already fetched are always stored whole, so an interrupted sync
+/// resumes from the first missing month on the next run.
+///
+/// Holds the country's advisory lock throughout, so concurrent syncs never
+/// walk the same zones. When another process holds it, either waits for it
+/// ([`SyncOptions::wait_for_lock`]) or skips the country.
+pub async fn sync_country(
+    conn: &mut PgConnection,
+    repo: &DataRepo,
+    country_code: &str,
+    options: &SyncOptions,
+    progress: &SyncProgress,
+    shutdown: &Shutdown,
+) -> SyncOutcome {
+    let key = db::country_lock_key(country_code);
+    let mut waiting = false;
+    let mut lock = loop {
+        match AdvisoryLock::try_acquire(&mut *conn, key) {
+            Ok(Some(lock)) => break lock,
+            Ok(None) if options.wait_for_lock && !shutdown.is_requested() => {
+                if !waiting {
+                    tracing::info!("[sync] {} is being synced by another process, waiting", country_code);
+                    waiting = true;
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(LOCK_POLL_INTERVAL) => {}
+                    _ = shutdown.requested() => {}
+                }
+            }
+            Ok(None) => {
+                tracing::info!("[sync] {} is being synced by another process, skipping", country_code);
+                return SyncOutcome::Skipped;
+            }
+            Err(e) => {
+                progress.error(format!("failed to lock {}: {}", country_code, e));
+                return SyncOutcome::Skipped;
+            }
+        }
+    };
 
-    let now = Utc::now().date_naive();
-    let end = NaiveDate::from_ymd_opt(now.year() + 1, 12, 31)
-        .expect("invalid year for end date");
+    sync_country_locked(&mut lock, repo, country_code, options, progress, shutdown).await;
+    SyncOutcome::Ran
+}
+
+/// [`sync_country`], once the country's lock is held.
+async fn sync_country_locked(
+    conn: &mut PgConnection,
+    repo: &DataRepo,
+    country_code: &str,
+    options: &SyncOptions,
+    progress: &SyncProgress,
+    shutdown: &Shutdown,
+) {
+    let started_at = Utc::now().naive_utc();
+    let now = started_at.date();
+    let end = options.last_month(now);
+    // `progress` may be shared with other countries
+    let errors_before = progress.error_count();
 
     // Sync zones first
-    let zones = match sync_zones(&client, conn, country_code).await {
+    let mut zones = match sync_zones(repo, conn, country_code, progress).await {
         Ok(z) => z,
         Err(e) => {
-            tracing::error!("[sync] failed to fetch zones for {}: {:?}", country_code, e);
+            progress.error(format!("failed to fetch zones for {}: {:?}", country_code, e));
             return;
         }
     };
+    if let Some(ref zone_code) = options.zone {
+        zones.retain(|z| z.zone_code == *zone_code);
+        if zones.is_empty() {
+            progress.error(format!("zone {} not found in {}", zone_code, country_code));
+            return;
+        }
+    }
+    progress.zones_total.fetch_add(zones.len(), Ordering::Relaxed);
+
+    if let Err(e) = sync_zone_geometry(repo, conn, country_code, &zones).await {
+        progress.error(format!("failed to sync zone geometry for {}: {:?}", country_code, e));
+    }
 
     tracing::info!("[sync] syncing prayer times for {} ({} zones)", country_code, zones.len());
 
     for zone in &zones {
-        sync_zone_prayer_times(&client, conn, &zone.country, zone, end).await;
+        if shutdown.is_requested() {
+            tracing::info!("[sync] interrupted before zone {}", zone.zone_code);
+            break;
+        }
+        let start = match options.from {
+            Some(from) => Ok(from),
+            None => resume_month(conn, &zone.zone_code, now),
+        };
+        match start {
+            Ok(start) => sync_zone_prayer_times(repo, conn, zone, start..=end, options.force, progress, shutdown).await,
+            Err(e) => progress.error(format!("db error for {}: {}", zone.zone_code, e)),
+        }
+        progress.zones_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Let API servers drop cached data for this country
+    if let Err(e) = db::notify_sync(conn, country_code) {
+        progress.error(format!("failed to notify sync for {}: {}", country_code, e));
+    }
+
+    // Only a complete, error-free run of the whole country counts for
+    // scheduling; otherwise the scheduler retries it
+    if !shutdown.is_requested() && !options.is_targeted() && progress.error_count() == errors_before {
+        let run = SyncRun {
+            country: country_code.to_string(),
+            started_at,
+            finished_at: Utc::now().naive_utc(),
+        };
+        if let Err(e) = upsert_sync_run(conn, &run) {
+            progress.error(format!("failed to record sync run for {}: {}", country_code, e));
+        }
     }
 
     tracing::info!("[sync] done for {}", country_code);
 }
 
-/// Sync all countries from the data repo.
-pub async fn sync_all(conn: &mut PgConnection) {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .expect("failed to build HTTP client");
-
-    let countries = match data_repo::fetch_countries(&client).await {
-        Ok(c) => c,
-        Err(e) => {
-            tracing::error!("[sync] failed to fetch countries: {:?}", e);
-            return;
-        }
-    };
+/// Sync the country list (countries.yaml) from the data repo.
+pub async fn sync_countries(
+    conn: &mut PgConnection,
+    repo: &DataRepo,
+    progress: &SyncProgress,
+) -> Result<Vec<data_repo::Country>, Box<dyn std::error::Error>> {
+    let countries = data_repo::fetch_countries(repo).await?;
 
     tracing::info!("[sync] found {} countries", countries.len());
 
     // Upsert countries to DB
     for country in &countries {
         if let Err(e) = countries::upsert_country(conn, country.into()) {
-            tracing::error!("[sync] db error upserting country {}: {}", country.code, e);
+            progress.error(format!("db error upserting country {}: {}", country.code, e));
         }
     }
+    Ok(countries)
+}
 
+/// Sync all countries from the data repo. Returns the countries skipped
+/// because they were locked (see [`sync_country`]).
+pub async fn sync_all(
+    conn: &mut PgConnection,
+    repo: &DataRepo,
+    options: &SyncOptions,
+    progress: &SyncProgress,
+    shutdown: &Shutdown,
+) -> Vec<String> {
+    let countries = match sync_countries(conn, repo, progress).await {
+        Ok(c) => c,
+        Err(e) => {
+            progress.error(format!("failed to fetch countries: {:?}", e));
+            return Vec::new();
+        }
+    };
+
+    let mut skipped = Vec::new();
     for country in &countries {
-        sync_country(conn, &country.code).await;
+        if shutdown.is_requested() {
+            tracing::info!("[sync] interrupted before country {}", country.code);
+            return skipped;
+        }
+        if sync_country(conn, repo, &country.code, options, progress, shutdown).await == SyncOutcome::Skipped {
+            skipped.push(country.code.clone());
+        }
     }
 
     tracing::info!("[sync] all done");
+    skipped
+}
+
+/// Runs [`sync_all`] every `interval` in the background, starting now, on a
+/// connection from `pool`. Replicas share [`SYNC_LOCK_KEY`]: a run is skipped
+/// while another process holds it, as is any country another sync is on.
+pub fn spawn_sync_scheduler(
+    pool: DbPool,
+    repo: DataRepo,
+    interval: Duration,
+    shutdown: Shutdown,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        tracing::info!("[sync] scheduled every {}s", interval.as_secs());
+        while !shutdown.is_requested() {
+            let lock = pool
+                .get()
+                .map_err(|e| e.to_string())
+                .and_then(|conn| AdvisoryLock::try_acquire(conn, SYNC_LOCK_KEY).map_err(|e| e.to_string()));
+            match lock {
+                Ok(Some(mut conn)) => {
+                    sync_all(&mut conn, &repo, &SyncOptions::default(), &SyncProgress::default(), &shutdown).await;
+                }
+                Ok(None) => tracing::info!("[sync] another process is syncing, skipping this run"),
+                Err(e) => tracing::error!("[sync] failed to start scheduled sync: {}", e),
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = shutdown.requested() => {}
+            }
+        }
+        tracing::info!("[sync] scheduler stopped");
+    })
 }
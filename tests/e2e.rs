@@ -59,6 +59,37 @@ async fn test_health_check() {
     assert_eq!(body.service, "simplesolat-api");
 }
 
+#[tokio::test]
+async fn test_sync_schedule_is_unversioned() {
+    let resp = reqwest::get(format!("{}/sync/schedule", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+
+    assert!(resp.status().is_success());
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["data"].is_array());
+
+    let resp = reqwest::get(format!("{}/v1/sync/schedule", BASE_URL)).await.unwrap();
+    assert_eq!(resp.status(), 404);
+}
+
+#[tokio::test]
+async fn test_admin_requires_admin_key() {
+    let client = reqwest::Client::new();
+    let resp = client.post(format!("{}/admin/sync", BASE_URL)).send().await.unwrap();
+    assert_eq!(resp.status(), 401);
+
+    let resp = client
+        .get(format!("{}/admin/jobs/00000000-0000-0000-0000-000000000000", BASE_URL))
+        .header("X-API-Key", "ss_not_a_real_key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["code"], "unauthorized");
+}
+
 #[tokio::test]
 async fn test_zones_returns_all_zones_with_country() {
     let resp = reqwest::get(format!("{}/zones", BASE_URL))
@@ -188,3 +219,255 @@ async fn test_prayer_times_unknown_zone_returns_empty() {
     let body: WaktuSolatResponse = resp.json().await.unwrap();
     assert!(body.data.is_empty(), "Unknown zone should return empty data");
 }
+
+#[derive(Debug, Deserialize)]
+struct Coverage {
+    first_date: Option<String>,
+    last_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZoneDetail {
+    zone: String,
+    country: String,
+    coverage: Coverage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZoneDetailResponse {
+    data: ZoneDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct CountryDetail {
+    code: String,
+    source: String,
+    zone_count: i64,
+    coverage: Coverage,
+}
+
+#[derive(Debug, Deserialize)]
+struct CountryDetailResponse {
+    data: CountryDetail,
+}
+
+#[tokio::test]
+async fn test_zone_detail_sgp01() {
+    let resp = reqwest::get(format!("{}/zones/SGP01", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+
+    assert!(resp.status().is_success());
+    let body: ZoneDetailResponse = resp.json().await.unwrap();
+    assert_eq!(body.data.zone, "SGP01");
+    assert_eq!(body.data.country, "SG");
+
+    let first = body.data.coverage.first_date.expect("SGP01 should have data");
+    let last = body.data.coverage.last_date.expect("SGP01 should have data");
+    assert!(first <= last);
+}
+
+#[tokio::test]
+async fn test_zone_detail_unknown_zone_returns_404() {
+    let resp = reqwest::get(format!("{}/zones/FAKE99", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+
+    assert_eq!(resp.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_country_detail_my() {
+    let resp = reqwest::get(format!("{}/countries/MY", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+
+    assert!(resp.status().is_success());
+    let body: CountryDetailResponse = resp.json().await.unwrap();
+    assert_eq!(body.data.code, "MY");
+    assert!(!body.data.source.is_empty());
+    assert!(body.data.zone_count >= 59, "Expected >= 59 MY zones, got {}", body.data.zone_count);
+    assert!(body.data.coverage.first_date.is_some());
+}
+
+#[derive(Debug, Deserialize)]
+struct ZoneCoverage {
+    zone: String,
+    first_date: Option<String>,
+    last_date: Option<String>,
+    total_days: i64,
+    missing_days: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZoneCoverageResponse {
+    data: ZoneCoverage,
+}
+
+#[derive(Debug, Deserialize)]
+struct CoverageResponse {
+    data: Vec<ZoneCoverage>,
+}
+
+#[tokio::test]
+async fn test_zone_coverage_sgr01() {
+    let resp = reqwest::get(format!("{}/zones/SGR01/coverage", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+
+    assert!(resp.status().is_success());
+    let body: ZoneCoverageResponse = resp.json().await.unwrap();
+    assert_eq!(body.data.zone, "SGR01");
+    assert!(body.data.first_date.is_some());
+    assert!(body.data.last_date.is_some());
+    assert!(body.data.total_days > 0);
+    assert!(body.data.missing_days >= 0);
+}
+
+#[tokio::test]
+async fn test_coverage_filtered_by_country() {
+    let resp = reqwest::get(format!("{}/coverage?country=SG", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+
+    assert!(resp.status().is_success());
+    let body: CoverageResponse = resp.json().await.unwrap();
+    assert!(body.data.iter().any(|c| c.zone == "SGP01"));
+    assert!(body.data.iter().all(|c| c.zone.starts_with("SGP")));
+}
+
+#[tokio::test]
+async fn test_openapi_spec_lists_prayer_times() {
+    let resp = reqwest::get(format!("{}/openapi.json", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+
+    assert!(resp.status().is_success());
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["openapi"].as_str().unwrap().starts_with("3."));
+    assert!(body["paths"]["/prayer-times/by-zone/{zone}"].is_object());
+    assert!(body["components"]["schemas"]["WaktuSolatResponse"].is_object());
+}
+
+#[tokio::test]
+async fn test_versioned_routes_are_not_deprecated() {
+    let resp = reqwest::get(format!("{}/v1/zones?country=SG", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+
+    assert!(resp.status().is_success());
+    assert!(resp.headers().get("deprecation").is_none());
+    let body: ZonesResponse = resp.json().await.unwrap();
+    assert!(body.data.iter().any(|z| z.zone == "SGP01"));
+
+    let resp = reqwest::get(format!("{}/v2/zones/SGP01", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+    assert!(resp.status().is_success());
+}
+
+#[tokio::test]
+async fn test_unversioned_routes_are_deprecated_aliases() {
+    let resp = reqwest::get(format!("{}/zones?country=SG", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+
+    assert!(resp.status().is_success());
+    assert!(resp.headers().get("deprecation").is_some());
+    assert!(resp.headers().get("sunset").is_some());
+    let link = resp.headers().get("link").unwrap().to_str().unwrap();
+    assert_eq!(link, "</v1/zones?country=SG>; rel=\"successor-version\"");
+}
+
+#[tokio::test]
+async fn test_invalid_range_returns_structured_error() {
+    let resp = reqwest::Client::new()
+        .get(format!(
+            "{}/v1/prayer-times/by-zone/SGR01?from=2026-02-01&to=2026-01-01",
+            BASE_URL
+        ))
+        .header("x-request-id", "e2e-invalid-range")
+        .send()
+        .await
+        .expect("Failed to connect to API");
+
+    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.headers()["x-request-id"], "e2e-invalid-range");
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["code"], "invalid_parameters");
+    assert_eq!(body["request_id"], "e2e-invalid-range");
+    assert_eq!(body["details"][0]["field"], "from");
+}
+
+#[tokio::test]
+async fn test_prayer_times_accepts_month_and_defaults() {
+    let resp = reqwest::get(format!("{}/v1/prayer-times/by-zone/SGR01?from=2026-02", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+
+    assert!(resp.status().is_success());
+    let body: WaktuSolatResponse = resp.json().await.unwrap();
+    assert_eq!(body.data.len(), 28);
+    assert_eq!(body.data.first().unwrap().date, "2026-02-01");
+    assert_eq!(body.data.last().unwrap().date, "2026-02-28");
+
+    let resp = reqwest::get(format!("{}/v1/prayer-times/by-zone/SGR01?from=today&to=%2B6d", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+    assert!(resp.status().is_success());
+}
+
+#[tokio::test]
+async fn test_relative_ranges_are_cached_until_midnight() {
+    let cache_control = |query: &'static str| async move {
+        let resp = reqwest::get(format!("{}/v1/prayer-times/by-zone/SGR01?{}", BASE_URL, query))
+            .await
+            .expect("Failed to connect to API");
+        assert!(resp.status().is_success());
+        resp.headers()["cache-control"].to_str().unwrap().to_string()
+    };
+
+    assert_eq!(cache_control("from=2026-01-01&to=2026-01-31").await, "public, max-age=86400");
+    assert_eq!(cache_control("from=2026-02").await, "public, max-age=86400");
+
+    for query in ["from=today", "from=2026-01-01&to=%2B6d", ""] {
+        let value = cache_control(query).await;
+        let max_age: i64 = value
+            .strip_prefix("public, max-age=")
+            .and_then(|age| age.parse().ok())
+            .unwrap_or_else(|| panic!("unexpected Cache-Control {} for '{}'", value, query));
+        assert!(max_age < 86400, "'{}' is cached past midnight: {}", query, value);
+    }
+}
+
+#[tokio::test]
+async fn test_malformed_date_returns_json_error() {
+    let resp = reqwest::get(format!("{}/v1/prayer-times/by-zone/SGR01?from=01-02-2026", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body["code"], "invalid_parameters");
+    assert_eq!(body["details"][0]["field"], "from");
+}
+
+#[tokio::test]
+async fn test_rate_limit_headers() {
+    let resp = reqwest::get(format!("{}/v1/prayer-times/by-zone/SGR01?from=2026-01&to=2026-12", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+
+    assert!(resp.status().is_success());
+    let limit: u64 = resp.headers()["ratelimit-limit"].to_str().unwrap().parse().unwrap();
+    let remaining: u64 = resp.headers()["ratelimit-remaining"].to_str().unwrap().parse().unwrap();
+    // A year of data costs 12 units
+    assert!(remaining <= limit - 12);
+    assert!(resp.headers()["ratelimit-policy"].to_str().unwrap().ends_with(";w=60"));
+    assert!(resp.headers().contains_key("ratelimit-reset"));
+
+    let resp = reqwest::get(format!("{}/health", BASE_URL))
+        .await
+        .expect("Failed to connect to API");
+    assert!(!resp.headers().contains_key("ratelimit-limit"));
+}
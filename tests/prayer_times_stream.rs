@@ -0,0 +1,82 @@
+//! A client that stops reading a long (streamed) prayer times response does
+//! not keep a database connection checked out.
+//!
+//! Syncs a year from an in-process fake data repo, then serves the app
+//! in-process with a single pooled connection. Requires a PostgreSQL
+//! database at `DATABASE_URL`:
+//!   cargo test --test prayer_times_stream
+
+mod common;
+
+use std::{
+    net::SocketAddr,
+    process::Stdio,
+    sync::{Arc, atomic::AtomicUsize},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use diesel::prelude::*;
+use simplesolat_api::{
+    api::data_repo::DataRepo,
+    config::Config,
+    models::db::connect_db,
+    routes::create_app_router,
+    service::{jobs::SyncJobs, shutdown::Shutdown},
+};
+use tower::ServiceExt;
+
+use common::{COUNTRY, ZONE, cleanup, database_url, spawn_fake_repo};
+
+/// Stores 2026 for [`ZONE`] through a real sync from the fake data repo.
+async fn sync_year() {
+    let repo_url = spawn_fake_repo(Arc::new(AtomicUsize::new(0)), Duration::ZERO).await;
+    let status = tokio::process::Command::new(env!("CARGO_BIN_EXE_simplesolat-api"))
+        .args(["sync", "--country", COUNTRY, "--from", "2026-01", "--to", "2026-12"])
+        .env("DATABASE_URL", database_url())
+        .env("DATA_REPO_URL", repo_url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .unwrap();
+    assert!(status.success(), "sync exited with {}", status);
+}
+
+#[tokio::test]
+async fn test_stalled_reader_releases_connection() {
+    let mut conn = PgConnection::establish(&database_url()).expect("DATABASE_URL must point to a test database");
+    cleanup(&mut conn);
+    sync_year().await;
+
+    let mut config = Config::default();
+    config.database.url = database_url();
+    config.database.pool_size = 1;
+    let pool = connect_db(&config.database);
+    let jobs = SyncJobs::spawn(pool.clone(), DataRepo::new(&config.data_repo), Shutdown::listen());
+    let app = create_app_router(&config, pool.clone(), jobs).await;
+
+    let mut request = Request::get(format!("/v1/prayer-times/by-zone/{}?from=2026-01-01&to=2026-12-31", ZONE))
+        .body(Body::empty())
+        .unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The body is not read yet, yet the only connection is free again
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let free = pool.get_timeout(Duration::from_secs(2)).is_ok();
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    cleanup(&mut conn);
+
+    assert!(free, "a stalled streamed response kept the connection checked out");
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 365);
+}
@@ -0,0 +1,107 @@
+//! Requests with a valid API key are limited by the key, not by their
+//! client IP's budget.
+//!
+//! Syncs three months from an in-process fake data repo, then serves the app
+//! in-process with a small per-IP budget. Requires a PostgreSQL database at
+//! `DATABASE_URL`:
+//!   cargo test --test rate_limits
+
+mod common;
+
+use std::{
+    net::SocketAddr,
+    process::Stdio,
+    sync::{Arc, atomic::AtomicUsize},
+    time::Duration,
+};
+
+use axum::{
+    Router,
+    body::Body,
+    extract::ConnectInfo,
+    http::{Request, StatusCode},
+};
+use diesel::prelude::*;
+use simplesolat_api::{
+    api::data_repo::DataRepo,
+    config::Config,
+    models::db::connect_db,
+    routes::create_app_router,
+    schema::{api_key_usage, api_keys},
+    service::{api_keys::create_key, jobs::SyncJobs, shutdown::Shutdown},
+};
+use tower::ServiceExt;
+
+use common::{COUNTRY, ZONE, cleanup, database_url, spawn_fake_repo};
+
+/// Stores the first quarter of 2026 for [`ZONE`] through a real sync.
+async fn sync_quarter() {
+    let repo_url = spawn_fake_repo(Arc::new(AtomicUsize::new(0)), Duration::ZERO).await;
+    let status = tokio::process::Command::new(env!("CARGO_BIN_EXE_simplesolat-api"))
+        .args(["sync", "--country", COUNTRY, "--from", "2026-01", "--to", "2026-03"])
+        .env("DATABASE_URL", database_url())
+        .env("DATA_REPO_URL", repo_url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .unwrap();
+    assert!(status.success(), "sync exited with {}", status);
+}
+
+/// Three months of prayer times, costing 3 units, from one client address.
+async fn get_quarter(app: &Router, key: Option<&str>) -> (StatusCode, Option<String>) {
+    let mut request = Request::get(format!("/v1/prayer-times/by-zone/{}?from=2026-01&to=2026-03", ZONE));
+    if let Some(key) = key {
+        request = request.header("X-API-Key", key);
+    }
+    let mut request = request.body(Body::empty()).unwrap();
+    request
+        .extensions_mut()
+        .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+    let response = app.clone().oneshot(request).await.unwrap();
+    let limit = response
+        .headers()
+        .get("ratelimit-limit")
+        .map(|v| v.to_str().unwrap().to_string());
+    (response.status(), limit)
+}
+
+#[tokio::test]
+async fn test_keyed_client_is_not_bound_by_ip_budget() {
+    let mut conn = PgConnection::establish(&database_url()).expect("DATABASE_URL must point to a test database");
+    cleanup(&mut conn);
+    sync_quarter().await;
+    let (key, record) = create_key(&mut conn, "rate limit test", 60, None, false).unwrap();
+
+    let mut config = Config::default();
+    config.database.url = database_url();
+    config.rate_limit.units_per_minute = 6;
+    let pool = connect_db(&config.database);
+    let jobs = SyncJobs::spawn(pool.clone(), DataRepo::new(&config.data_repo), Shutdown::listen());
+    let app = create_app_router(&config, pool, jobs).await;
+
+    // 15 units from one IP: over its budget, within the key's
+    let mut keyed = Vec::new();
+    for _ in 0..5 {
+        keyed.push(get_quarter(&app, Some(&key)).await);
+    }
+    let mut anonymous = Vec::new();
+    for _ in 0..3 {
+        anonymous.push(get_quarter(&app, None).await.0);
+    }
+
+    diesel::delete(api_key_usage::table.filter(api_key_usage::api_key_id.eq(record.id)))
+        .execute(&mut conn)
+        .unwrap();
+    diesel::delete(api_keys::table.filter(api_keys::id.eq(record.id)))
+        .execute(&mut conn)
+        .unwrap();
+    cleanup(&mut conn);
+
+    for (status, limit) in keyed {
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(limit.as_deref(), Some("60"), "keyed responses should report the key's budget");
+    }
+    assert_eq!(anonymous, [StatusCode::OK, StatusCode::OK, StatusCode::TOO_MANY_REQUESTS]);
+}
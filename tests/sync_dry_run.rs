@@ -0,0 +1,96 @@
+//! `sync --dry-run` reports what a sync would change and writes nothing.
+//!
+//! Runs the real binary against an in-process fake data repo. Requires a
+//! PostgreSQL database at `DATABASE_URL`:
+//!   cargo test --test sync_dry_run
+
+mod common;
+
+use std::{
+    sync::{Arc, atomic::AtomicUsize},
+    time::Duration,
+};
+
+use chrono::{NaiveDate, NaiveTime};
+use diesel::prelude::*;
+use simplesolat_api::schema::{prayer_times, zones};
+
+use common::{COUNTRY, ZONE, cleanup, database_url, spawn_fake_repo};
+
+async fn sync(repo_url: &str, extra: &[&str]) -> std::process::Output {
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_simplesolat-api"))
+        .args(["sync", "--country", COUNTRY])
+        .args(extra)
+        .env("DATABASE_URL", database_url())
+        .env("DATA_REPO_URL", repo_url)
+        .env("RUST_LOG", "warn")
+        .output()
+        .await
+        .unwrap();
+    assert!(output.status.success(), "sync {:?} exited with {}", extra, output.status);
+    output
+}
+
+fn stored_rows(conn: &mut PgConnection) -> Vec<(NaiveDate, NaiveTime)> {
+    prayer_times::table
+        .filter(prayer_times::zone_code.eq(ZONE))
+        .select((prayer_times::date, prayer_times::fajr))
+        .order(prayer_times::date.asc())
+        .load(conn)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_dry_run_reports_diff_without_writing() {
+    let mut conn = PgConnection::establish(&database_url()).expect("DATABASE_URL must point to a test database");
+    cleanup(&mut conn);
+
+    let repo_url = spawn_fake_repo(Arc::new(AtomicUsize::new(0)), Duration::ZERO).await;
+    sync(&repo_url, &["--from", "2026-01", "--to", "2026-01"]).await;
+
+    let day = |d| NaiveDate::from_ymd_opt(2026, 1, d).unwrap();
+    let zone_rows = prayer_times::table.filter(prayer_times::zone_code.eq(ZONE));
+    diesel::update(zone_rows.filter(prayer_times::date.eq(day(10))))
+        .set(prayer_times::fajr.eq(NaiveTime::from_hms_opt(4, 0, 0).unwrap()))
+        .execute(&mut conn)
+        .unwrap();
+    diesel::delete(zone_rows.filter(prayer_times::date.eq(day(20))))
+        .execute(&mut conn)
+        .unwrap();
+    diesel::update(zones::table.find(ZONE))
+        .set(zones::location.eq("Old"))
+        .execute(&mut conn)
+        .unwrap();
+    let before = stored_rows(&mut conn);
+
+    let output = sync(&repo_url, &["--from", "2026-01", "--to", "2026-02", "--dry-run", "--json"]).await;
+    let after = stored_rows(&mut conn);
+    let location: String = zones::table.find(ZONE).select(zones::location).first(&mut conn).unwrap();
+    cleanup(&mut conn);
+
+    assert_eq!(after, before, "dry run changed prayer times");
+    assert_eq!(location, "Old", "dry run changed the zone");
+
+    let diff: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(diff["errors"], serde_json::json!([]));
+    let country = &diff["countries"][0];
+    assert_eq!(country["country"], COUNTRY);
+    assert_eq!(country["new_zones"], serde_json::json!([]));
+    assert_eq!(
+        country["renamed_zones"],
+        serde_json::json!([{"zone": ZONE, "field": "location", "stored": "Old", "fetched": "Test"}])
+    );
+    assert_eq!(
+        country["prayer_times"],
+        serde_json::json!([{
+            "zone": ZONE,
+            "inserted": [
+                {"from": "2026-01-20", "to": "2026-01-20"},
+                {"from": "2026-02-01", "to": "2026-02-28"},
+            ],
+            "changed": [
+                {"date": "2026-01-10", "prayer": "fajr", "stored": "04:00:00", "fetched": "06:00:00"},
+            ],
+        }])
+    );
+}
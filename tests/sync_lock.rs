@@ -0,0 +1,77 @@
+//! A country locked by another sync is never synced concurrently.
+//!
+//! Holds the country's advisory lock from the test, then runs the real
+//! binary against an in-process fake data repo. Requires a PostgreSQL
+//! database at `DATABASE_URL`:
+//!   cargo test --test sync_lock
+
+mod common;
+
+use std::{
+    process::Stdio,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use diesel::{prelude::*, sql_types::BigInt};
+use simplesolat_api::models::db::{country_lock_key, select_sync_locks};
+
+use common::{COUNTRY, cleanup, database_url, spawn_fake_repo};
+
+fn sync_command(repo_url: &str, extra: &[&str]) -> tokio::process::Command {
+    let mut command = tokio::process::Command::new(env!("CARGO_BIN_EXE_simplesolat-api"));
+    command
+        .args(["sync", "--country", COUNTRY])
+        .args(extra)
+        .env("DATABASE_URL", database_url())
+        .env("DATA_REPO_URL", repo_url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    command
+}
+
+fn set_lock(conn: &mut PgConnection, function: &str) {
+    diesel::sql_query(format!("SELECT {}($1)", function))
+        .bind::<BigInt, _>(country_lock_key(COUNTRY))
+        .execute(conn)
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_locked_country_is_not_synced() {
+    let mut conn = PgConnection::establish(&database_url()).expect("DATABASE_URL must point to a test database");
+    cleanup(&mut conn);
+
+    let started = Arc::new(AtomicUsize::new(0));
+    let repo_url = spawn_fake_repo(started.clone(), Duration::ZERO).await;
+
+    // Another sync is on this country
+    set_lock(&mut conn, "pg_advisory_lock");
+    assert!(select_sync_locks(&mut conn).unwrap().countries.contains(&COUNTRY.to_string()));
+
+    let status = sync_command(&repo_url, &[]).status().await.unwrap();
+    assert!(!status.success(), "a locked country should fail the sync by default");
+
+    let status = sync_command(&repo_url, &["--skip-if-locked"]).status().await.unwrap();
+    assert!(status.success(), "sync --skip-if-locked exited with {}", status);
+    assert_eq!(started.load(Ordering::SeqCst), 0);
+
+    // Waits while the lock is held, then syncs once it is released
+    let mut waiting = sync_command(&repo_url, &["--wait"]).spawn().unwrap();
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert!(waiting.try_wait().unwrap().is_none(), "sync --wait did not wait for the lock");
+    assert_eq!(started.load(Ordering::SeqCst), 0);
+
+    set_lock(&mut conn, "pg_advisory_unlock");
+    let status = tokio::time::timeout(Duration::from_secs(30), waiting.wait())
+        .await
+        .expect("sync --wait did not finish after the lock was released")
+        .unwrap();
+    cleanup(&mut conn);
+
+    assert!(status.success(), "sync --wait exited with {}", status);
+    assert!(started.load(Ordering::SeqCst) > 0);
+}
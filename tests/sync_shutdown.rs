@@ -0,0 +1,76 @@
+//! Interrupting a sync with SIGTERM must leave only whole months stored.
+//!
+//! Runs the real binary against a slow, in-process fake data repo. Requires
+//! a PostgreSQL database at `DATABASE_URL`:
+//!   cargo test --test sync_shutdown
+
+mod common;
+
+use std::{
+    collections::BTreeMap,
+    process::Stdio,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::{Datelike, NaiveDate};
+use diesel::prelude::*;
+use simplesolat_api::schema::prayer_times;
+
+use common::{ZONE, cleanup, database_url, month_records, spawn_fake_repo};
+
+const MONTH_DELAY: Duration = Duration::from_millis(300);
+
+#[tokio::test]
+async fn test_sigterm_leaves_no_partial_month() {
+    let mut conn = PgConnection::establish(&database_url()).expect("DATABASE_URL must point to a test database");
+    cleanup(&mut conn);
+
+    let started = Arc::new(AtomicUsize::new(0));
+    let repo_url = spawn_fake_repo(started.clone(), MONTH_DELAY).await;
+
+    let mut child = tokio::process::Command::new(env!("CARGO_BIN_EXE_simplesolat-api"))
+        .args(["sync", "--country", "ZZ"])
+        .env("DATABASE_URL", database_url())
+        .env("DATA_REPO_URL", &repo_url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // Signal while the third month is being fetched
+    while started.load(Ordering::SeqCst) < 3 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let pid = child.id().unwrap().to_string();
+    let killed = std::process::Command::new("kill").args(["-TERM", &pid]).status().unwrap();
+    assert!(killed.success());
+
+    let status = tokio::time::timeout(Duration::from_secs(10), child.wait())
+        .await
+        .expect("sync did not stop after SIGTERM")
+        .unwrap();
+    assert!(status.success(), "sync exited with {}", status);
+
+    let dates: Vec<NaiveDate> = prayer_times::table
+        .filter(prayer_times::zone_code.eq(ZONE))
+        .select(prayer_times::date)
+        .load(&mut conn)
+        .unwrap();
+    cleanup(&mut conn);
+
+    let mut months: BTreeMap<(i32, u32), usize> = BTreeMap::new();
+    for date in &dates {
+        *months.entry((date.year(), date.month())).or_default() += 1;
+    }
+    for ((year, month), days) in &months {
+        let expected = month_records(*year, *month).as_array().unwrap().len();
+        assert_eq!(*days, expected, "partial month {}-{:02}", year, month);
+    }
+    // The month in flight is finished, and nothing is fetched after it
+    assert_eq!(months.len(), 3);
+    assert_eq!(started.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,115 @@
+//! `sync --zone --from --to` fetches only the given months, and `--force`
+//! overwrites stored days that differ from the data repo. Codes are matched
+//! whatever their case.
+//!
+//! Runs the real binary against an in-process fake data repo. Requires a
+//! PostgreSQL database at `DATABASE_URL`:
+//!   cargo test --test sync_targeted
+
+mod common;
+
+use std::{
+    process::Stdio,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use diesel::prelude::*;
+use simplesolat_api::schema::prayer_times;
+
+use common::{ZONE, cleanup, database_url, spawn_fake_repo};
+
+async fn sync(repo_url: &str, extra: &[&str]) {
+    sync_args(repo_url, &[&["--country", common::COUNTRY, "--zone", ZONE], extra].concat()).await;
+}
+
+async fn sync_args(repo_url: &str, args: &[&str]) {
+    let status = tokio::process::Command::new(env!("CARGO_BIN_EXE_simplesolat-api"))
+        .arg("sync")
+        .args(args)
+        .env("DATABASE_URL", database_url())
+        .env("DATA_REPO_URL", repo_url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .unwrap();
+    assert!(status.success(), "sync {:?} exited with {}", args, status);
+}
+
+/// Fajr on `date`, with the row's `created_at` and `updated_at`.
+fn fajr_on(conn: &mut PgConnection, date: NaiveDate) -> (NaiveTime, NaiveDateTime, NaiveDateTime) {
+    prayer_times::table
+        .filter(prayer_times::zone_code.eq(ZONE))
+        .filter(prayer_times::date.eq(date))
+        .select((prayer_times::fajr, prayer_times::created_at, prayer_times::updated_at))
+        .first(conn)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_force_overwrites_months_in_range() {
+    let mut conn = PgConnection::establish(&database_url()).expect("DATABASE_URL must point to a test database");
+    cleanup(&mut conn);
+
+    let started = Arc::new(AtomicUsize::new(0));
+    let repo_url = spawn_fake_repo(started.clone(), Duration::ZERO).await;
+
+    sync(&repo_url, &["--from", "2026-01", "--to", "2026-02"]).await;
+    assert_eq!(started.load(Ordering::SeqCst), 2);
+    let stored: i64 = prayer_times::table
+        .filter(prayer_times::zone_code.eq(ZONE))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(stored, 31 + 28);
+
+    let broken = NaiveDate::from_ymd_opt(2026, 2, 10).unwrap();
+    let untouched = NaiveDate::from_ymd_opt(2026, 2, 11).unwrap();
+    let wrong = NaiveTime::from_hms_opt(4, 0, 0).unwrap();
+    diesel::update(
+        prayer_times::table
+            .filter(prayer_times::zone_code.eq(ZONE))
+            .filter(prayer_times::date.eq(broken)),
+    )
+    .set(prayer_times::fajr.eq(wrong))
+    .execute(&mut conn)
+    .unwrap();
+    let (_, broken_created, broken_updated) = fajr_on(&mut conn, broken);
+    let untouched_before = fajr_on(&mut conn, untouched);
+
+    // Without --force, stored days are kept
+    sync(&repo_url, &["--from", "2026-02", "--to", "2026-02"]).await;
+    assert_eq!(fajr_on(&mut conn, broken).0, wrong);
+
+    sync(&repo_url, &["--from", "2026-02", "--to", "2026-02", "--force"]).await;
+    let (fixed, fixed_created, fixed_updated) = fajr_on(&mut conn, broken);
+    let untouched_after = fajr_on(&mut conn, untouched);
+
+    // Lowercase codes, with the zone's country looked up and given
+    let zone = ZONE.to_lowercase();
+    sync_args(&repo_url, &["--zone", &zone, "--from", "2026-03", "--to", "2026-03"]).await;
+    let country = common::COUNTRY.to_lowercase();
+    sync_args(
+        &repo_url,
+        &["--country", &country, "--zone", &zone, "--from", "2026-04", "--to", "2026-04"],
+    )
+    .await;
+    let stored_after: i64 = prayer_times::table
+        .filter(prayer_times::zone_code.eq(ZONE))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    cleanup(&mut conn);
+
+    assert_eq!(fixed, NaiveTime::from_hms_opt(6, 0, 0).unwrap());
+    assert_eq!(fixed_created, broken_created, "overwriting should keep created_at");
+    assert!(fixed_updated > broken_updated);
+    assert_eq!(untouched_after, untouched_before, "unchanged days should not be rewritten");
+    assert_eq!(stored_after, 31 + 28 + 31 + 30);
+    assert_eq!(started.load(Ordering::SeqCst), 6);
+}
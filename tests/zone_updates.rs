@@ -0,0 +1,69 @@
+//! A sync moves a zone's `updated_at` (and so the zone list's
+//! `Last-Modified`) only when the zone's details changed.
+//!
+//! Syncs from an in-process fake data repo with the real binary. Requires a
+//! PostgreSQL database at `DATABASE_URL`:
+//!   cargo test --test zone_updates
+
+mod common;
+
+use std::{
+    process::Stdio,
+    sync::{Arc, atomic::AtomicUsize},
+    time::Duration,
+};
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use simplesolat_api::schema::zones;
+
+use common::{COUNTRY, ZONE, cleanup, database_url, spawn_fake_repo};
+
+async fn sync(repo_url: &str) {
+    let status = tokio::process::Command::new(env!("CARGO_BIN_EXE_simplesolat-api"))
+        .args(["sync", "--country", COUNTRY, "--from", "2026-01", "--to", "2026-01"])
+        .env("DATABASE_URL", database_url())
+        .env("DATA_REPO_URL", repo_url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .unwrap();
+    assert!(status.success(), "sync exited with {}", status);
+}
+
+fn zone_versions(conn: &mut PgConnection) -> (String, NaiveDateTime, NaiveDateTime) {
+    zones::table
+        .find(ZONE)
+        .select((zones::state, zones::created_at, zones::updated_at))
+        .first(conn)
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_sync_updates_changed_zones_only() {
+    let mut conn = PgConnection::establish(&database_url()).expect("DATABASE_URL must point to a test database");
+    cleanup(&mut conn);
+    let repo_url = spawn_fake_repo(Arc::new(AtomicUsize::new(0)), Duration::ZERO).await;
+
+    sync(&repo_url).await;
+    let first = zone_versions(&mut conn);
+
+    // Nothing changed in the data repo
+    sync(&repo_url).await;
+    let unchanged = zone_versions(&mut conn);
+
+    // The stored zone differs from the data repo, as after a rename upstream
+    diesel::update(zones::table.find(ZONE))
+        .set(zones::state.eq("Old name"))
+        .execute(&mut conn)
+        .unwrap();
+    sync(&repo_url).await;
+    let renamed = zone_versions(&mut conn);
+    cleanup(&mut conn);
+
+    assert_eq!(unchanged, first, "an unchanged zone should not be rewritten");
+    assert_eq!(renamed.0, first.0);
+    assert_eq!(renamed.1, first.1, "created_at should be kept");
+    assert!(renamed.2 > first.2, "updated_at should move when the zone changes");
+}